@@ -0,0 +1,43 @@
+use syn::{spanned::Spanned, ImplItemConst, LitStr};
+
+/// An associated const in the impl block that has been marked for export with
+/// `#[js_constant]`. These always go on the constructor, like `Number.MAX_SAFE_INTEGER`.
+pub(crate) struct ExportedConst {
+    pub(crate) js_name: String,
+    pub(crate) ident: syn::Ident,
+}
+
+impl ExportedConst {
+    /// Same as ExportedFn::take_from: removes our attribute if there is one.
+    pub(crate) fn take_from(item: &mut ImplItemConst) -> syn::Result<Option<Self>> {
+        let is_ours = |attr: &syn::Attribute| attr.path().is_ident("js_constant");
+        let attr = match item.attrs.iter().position(is_ours) {
+            Some(index) => item.attrs.remove(index),
+            None => return Ok(None),
+        };
+
+        if let Some(duplicate) = item.attrs.iter().find(|attr| is_ours(attr)) {
+            return Err(syn::Error::new(
+                duplicate.span(),
+                "a constant can only be exported once",
+            ));
+        }
+
+        let mut js_name = item.ident.to_string();
+        if let syn::Meta::List(_) = attr.meta {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    js_name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported property"))
+                }
+            })?;
+        }
+
+        Ok(Some(ExportedConst {
+            js_name,
+            ident: item.ident.clone(),
+        }))
+    }
+}
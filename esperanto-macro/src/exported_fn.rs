@@ -0,0 +1,172 @@
+use syn::{
+    spanned::Spanned, Attribute, FnArg, GenericArgument, ImplItemFn, LitStr, PathArguments,
+    ReturnType, Type,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum ExportKind {
+    Constructor,
+    Function,
+    Getter,
+    Setter,
+}
+
+impl ExportKind {
+    fn from_attribute(attr: &Attribute) -> Option<Self> {
+        let path = attr.path();
+        if path.is_ident("js_constructor") {
+            Some(ExportKind::Constructor)
+        } else if path.is_ident("js_function") {
+            Some(ExportKind::Function)
+        } else if path.is_ident("js_getter") {
+            Some(ExportKind::Getter)
+        } else if path.is_ident("js_setter") {
+            Some(ExportKind::Setter)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Receiver {
+    // A static function, i.e. one that doesn't take self at all
+    None,
+    Ref,
+    Mut,
+}
+
+/// A function in the impl block that has been marked for export.
+pub(crate) struct ExportedFn {
+    pub(crate) kind: ExportKind,
+    pub(crate) js_name: String,
+    pub(crate) ident: syn::Ident,
+    pub(crate) receiver: Receiver,
+    pub(crate) arguments: Vec<Type>,
+    /// Whether the function returns a Result that needs to be unwrapped with `?`
+    pub(crate) fallible: bool,
+}
+
+impl ExportedFn {
+    /// Looks for one of our attributes on the function. If it has one the attribute is removed
+    /// (since it isn't a real attribute macro) and the function is parsed for export.
+    pub(crate) fn take_from(func: &mut ImplItemFn) -> syn::Result<Option<Self>> {
+        let position = func
+            .attrs
+            .iter()
+            .position(|attr| ExportKind::from_attribute(attr).is_some());
+
+        let attr = match position {
+            Some(index) => func.attrs.remove(index),
+            None => return Ok(None),
+        };
+
+        if let Some(duplicate) = func
+            .attrs
+            .iter()
+            .find(|attr| ExportKind::from_attribute(attr).is_some())
+        {
+            return Err(syn::Error::new(
+                duplicate.span(),
+                "a function can only be exported once",
+            ));
+        }
+
+        let kind = ExportKind::from_attribute(&attr).unwrap();
+        let mut js_name = func.sig.ident.to_string();
+
+        // All of our attributes can be used bare, i.e. #[js_function] rather than
+        // #[js_function(name = "...")], in which case the Rust name is used.
+        if let syn::Meta::List(_) = attr.meta {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") && kind != ExportKind::Constructor {
+                    js_name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported property"))
+                }
+            })?;
+        }
+
+        if !func.sig.generics.params.is_empty() {
+            return Err(syn::Error::new(
+                func.sig.generics.span(),
+                "exported functions can't be generic",
+            ));
+        }
+
+        let mut receiver = Receiver::None;
+        let mut arguments = Vec::new();
+
+        for input in &func.sig.inputs {
+            match input {
+                FnArg::Receiver(self_arg) => {
+                    if self_arg.reference.is_none() {
+                        return Err(syn::Error::new(
+                            self_arg.span(),
+                            "exported functions must take self by reference",
+                        ));
+                    }
+                    receiver = match self_arg.mutability {
+                        Some(_) => Receiver::Mut,
+                        None => Receiver::Ref,
+                    };
+                }
+                FnArg::Typed(typed) => arguments.push((*typed.ty).clone()),
+            }
+        }
+
+        let fallible = match &func.sig.output {
+            ReturnType::Default => false,
+            ReturnType::Type(_, ty) => result_ok_type(ty).is_some(),
+        };
+
+        let exported = ExportedFn {
+            kind,
+            js_name,
+            ident: func.sig.ident.clone(),
+            receiver,
+            arguments,
+            fallible,
+        };
+
+        exported.validate(func)?;
+        Ok(Some(exported))
+    }
+
+    fn validate(&self, func: &ImplItemFn) -> syn::Result<()> {
+        let error = |message: &str| Err(syn::Error::new(func.sig.span(), message));
+        match self.kind {
+            ExportKind::Constructor if self.receiver != Receiver::None => {
+                error("constructors can't take self")
+            }
+            ExportKind::Getter if !self.arguments.is_empty() => {
+                error("getters can't take any arguments")
+            }
+            ExportKind::Setter if self.arguments.len() != 1 => {
+                error("setters must take exactly one argument")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// We can't know for sure whether a type is a Result from syntax alone, but anything named
+// Result or EsperantoResult is close enough.
+fn result_ok_type(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(type_path) => &type_path.path,
+        _ => return None,
+    };
+    let last = path.segments.last()?;
+    if last.ident != "Result" && last.ident != "EsperantoResult" {
+        return None;
+    }
+    match &last.arguments {
+        PathArguments::AngleBracketed(generics) => match generics.args.first()? {
+            GenericArgument::Type(ok_type) => Some(ok_type),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,300 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{spanned::Spanned, ImplItem, ItemImpl, LitStr, Type};
+
+use crate::exported_const::ExportedConst;
+use crate::exported_fn::{ExportKind, ExportedFn, Receiver};
+
+pub(crate) fn export_impl(
+    mut item_impl: ItemImpl,
+    class_name: Option<LitStr>,
+) -> syn::Result<TokenStream> {
+    if !item_impl.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            item_impl.generics.span(),
+            "js_export doesn't support generic types",
+        ));
+    }
+    if let Some((_, path, _)) = &item_impl.trait_ {
+        return Err(syn::Error::new(
+            path.span(),
+            "js_export must be used on an inherent impl block, not a trait impl",
+        ));
+    }
+
+    let mut exported = Vec::new();
+    let mut constants = Vec::new();
+    for item in item_impl.items.iter_mut() {
+        match item {
+            ImplItem::Fn(func) => {
+                if let Some(exported_fn) = ExportedFn::take_from(func)? {
+                    exported.push(exported_fn);
+                }
+            }
+            ImplItem::Const(constant) => {
+                if let Some(exported_const) = ExportedConst::take_from(constant)? {
+                    constants.push(exported_const);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let self_ty = &*item_impl.self_ty;
+    let class_name = match class_name {
+        Some(name) => name.value(),
+        None => type_name(self_ty)?,
+    };
+
+    let mut constructor: Option<TokenStream> = None;
+    for exported_fn in exported.iter() {
+        if exported_fn.kind == ExportKind::Constructor {
+            if constructor.is_some() {
+                return Err(syn::Error::new(
+                    exported_fn.ident.span(),
+                    "a class can only have one constructor",
+                ));
+            }
+            constructor = Some(constructor_tokens(self_ty, exported_fn));
+        }
+    }
+
+    let constructor = constructor.map(|tokens| {
+        quote! {
+            const CALL_AS_CONSTRUCTOR: Option<::esperanto::export::JSClassFunction> = Some(#tokens);
+        }
+    });
+
+    let attributes = attributes_tokens(self_ty, &exported, &[], false)?.map(|map| {
+        quote! { const ATTRIBUTES: ::esperanto::export::JSExportAttributes = #map; }
+    });
+    let static_attributes = attributes_tokens(self_ty, &exported, &constants, true)?.map(|map| {
+        quote! { const STATIC_ATTRIBUTES: ::esperanto::export::JSExportAttributes = #map; }
+    });
+
+    Ok(quote! {
+        #item_impl
+
+        impl ::esperanto::JSExportClass for #self_ty {
+            const CLASS_NAME: &'static str = #class_name;
+            #constructor
+            #attributes
+            #static_attributes
+        }
+    })
+}
+
+fn type_name(ty: &Type) -> syn::Result<String> {
+    match ty {
+        Type::Path(type_path) => match type_path.path.segments.last() {
+            Some(segment) => Ok(segment.ident.to_string()),
+            None => Err(syn::Error::new(
+                ty.span(),
+                "could not work out a class name",
+            )),
+        },
+        _ => Err(syn::Error::new(
+            ty.span(),
+            "could not work out a class name, specify one with #[js_export(name = \"...\")]",
+        )),
+    }
+}
+
+// Creates a phf map of every method or property that is (or isn't) static, plus any constants.
+// Returns None if there aren't any so we can leave the trait default in place.
+fn attributes_tokens(
+    self_ty: &Type,
+    exported: &[ExportedFn],
+    constants: &[ExportedConst],
+    is_static: bool,
+) -> syn::Result<Option<TokenStream>> {
+    let matching = exported.iter().filter(|exported_fn| {
+        exported_fn.kind != ExportKind::Constructor
+            && (exported_fn.receiver == Receiver::None) == is_static
+    });
+
+    let mut entries: Vec<TokenStream> = Vec::new();
+    let mut seen_names: Vec<&str> = Vec::new();
+
+    for exported_fn in matching.clone() {
+        let name = exported_fn.js_name.as_str();
+        match exported_fn.kind {
+            ExportKind::Function => {
+                if seen_names.contains(&name) {
+                    return Err(duplicate_name_error(exported_fn));
+                }
+                seen_names.push(name);
+                let method = method_tokens(self_ty, exported_fn);
+                entries.push(quote! {
+                    #name => ::esperanto::export::JSExportAttribute::Function(#method)
+                });
+            }
+            ExportKind::Getter => {
+                if seen_names.contains(&name) {
+                    return Err(duplicate_name_error(exported_fn));
+                }
+                seen_names.push(name);
+                let getter = getter_tokens(self_ty, exported_fn);
+                let setter = match matching
+                    .clone()
+                    .find(|other| other.kind == ExportKind::Setter && other.js_name == name)
+                {
+                    Some(setter_fn) => {
+                        let setter = setter_tokens(self_ty, setter_fn);
+                        quote! { Some(#setter) }
+                    }
+                    None => quote! { None },
+                };
+                entries.push(quote! {
+                    #name => ::esperanto::export::JSExportAttribute::Property {
+                        getter: #getter,
+                        setter: #setter,
+                    }
+                });
+            }
+            ExportKind::Setter => {
+                let has_getter = matching
+                    .clone()
+                    .any(|other| other.kind == ExportKind::Getter && other.js_name == name);
+                if !has_getter {
+                    return Err(syn::Error::new(
+                        exported_fn.ident.span(),
+                        format!("setter for \"{}\" has no matching getter", name),
+                    ));
+                }
+            }
+            ExportKind::Constructor => {}
+        }
+    }
+
+    for constant in constants {
+        let name = constant.js_name.as_str();
+        if seen_names.contains(&name) {
+            return Err(syn::Error::new(
+                constant.ident.span(),
+                format!("\"{}\" has already been exported", name),
+            ));
+        }
+        seen_names.push(name);
+        let ident = &constant.ident;
+        entries.push(quote! {
+            #name => ::esperanto::export::JSExportAttribute::Constant(|ctx| {
+                ::esperanto::JSValue::try_new_from(<#self_ty>::#ident, ctx)
+            })
+        });
+    }
+
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    // phf_ordered_map! expects the phf crate to be available at the call site, which it may
+    // well not be, so we bring in the one esperanto uses.
+    Ok(Some(quote! {
+        Some({
+            use ::esperanto::export::phf;
+            phf::phf_ordered_map!(#(#entries),*)
+        })
+    }))
+}
+
+fn duplicate_name_error(exported_fn: &ExportedFn) -> syn::Error {
+    syn::Error::new(
+        exported_fn.ident.span(),
+        format!("\"{}\" has already been exported", exported_fn.js_name),
+    )
+}
+
+// Converts the JS arguments into the types the Rust function expects. FromJSArgs treats
+// missing arguments as undefined and turns conversion failures into TypeErrors.
+fn argument_conversions(exported_fn: &ExportedFn) -> (TokenStream, Vec<syn::Ident>) {
+    let names: Vec<syn::Ident> = (0..exported_fn.arguments.len())
+        .map(|index| format_ident!("arg{}", index))
+        .collect();
+    let types = &exported_fn.arguments;
+
+    let tokens = quote! {
+        let (#(#names,)*) =
+            <(#(#types,)*) as ::esperanto::FromJSArgs>::from_js_args(args, ctx)?;
+    };
+    (tokens, names)
+}
+
+fn call_tokens(self_ty: &Type, exported_fn: &ExportedFn, arg_names: &[syn::Ident]) -> TokenStream {
+    let ident = &exported_fn.ident;
+    let call = match exported_fn.receiver {
+        Receiver::None => quote! { <#self_ty>::#ident(#(#arg_names),*) },
+        Receiver::Ref => quote! { this_obj.as_native::<#self_ty>()?.#ident(#(#arg_names),*) },
+        Receiver::Mut => {
+            quote! { this_obj.as_native_mut::<#self_ty>()?.#ident(#(#arg_names),*) }
+        }
+    };
+    match exported_fn.fallible {
+        true => quote! { #call? },
+        false => call,
+    }
+}
+
+// The body shared by methods and getters: call the function, then convert whatever it returns.
+// Functions that return nothing give back (), which converts to undefined.
+fn return_tokens(call: TokenStream) -> TokenStream {
+    quote! {
+        let result = #call;
+        ::esperanto::JSValue::try_new_from(result, ctx)
+    }
+}
+
+fn constructor_tokens(self_ty: &Type, exported_fn: &ExportedFn) -> TokenStream {
+    let (conversions, arg_names) = argument_conversions(exported_fn);
+    let call = call_tokens(self_ty, exported_fn, &arg_names);
+    let num_args = exported_fn.arguments.len() as i32;
+    quote! {
+        ::esperanto::export::JSClassFunction {
+            num_args: #num_args,
+            func: |args, ctx| {
+                #conversions
+                let instance: #self_ty = #call;
+                ::esperanto::JSValue::new_wrapped_native(instance, ctx)
+            },
+        }
+    }
+}
+
+fn method_tokens(self_ty: &Type, exported_fn: &ExportedFn) -> TokenStream {
+    let (conversions, arg_names) = argument_conversions(exported_fn);
+    let body = return_tokens(call_tokens(self_ty, exported_fn, &arg_names));
+    let num_args = exported_fn.arguments.len() as i32;
+    quote! {
+        ::esperanto::export::JSClassMethod {
+            num_args: #num_args,
+            func: |ctx, this_obj, args| {
+                #conversions
+                #body
+            },
+        }
+    }
+}
+
+fn getter_tokens(self_ty: &Type, exported_fn: &ExportedFn) -> TokenStream {
+    let body = return_tokens(call_tokens(self_ty, exported_fn, &[]));
+    quote! {
+        |ctx, this_obj| {
+            #body
+        }
+    }
+}
+
+// The new value goes through the same conversion as function arguments so that assigning
+// something unconvertible gives the same TypeError
+fn setter_tokens(self_ty: &Type, exported_fn: &ExportedFn) -> TokenStream {
+    let (conversions, arg_names) = argument_conversions(exported_fn);
+    let call = call_tokens(self_ty, exported_fn, &arg_names);
+    quote! {
+        |ctx, this_obj, value| {
+            let args: &[&::esperanto::JSValue] = &[value];
+            #conversions
+            #call;
+            Ok(())
+        }
+    }
+}
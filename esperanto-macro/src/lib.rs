@@ -0,0 +1,47 @@
+//! Procedural macros for esperanto. You probably want to use these via `esperanto::export`
+//! rather than depending on this crate directly.
+//!
+//! `#[js_export]` goes on an `impl` block and generates a `JSExportClass` implementation
+//! from the functions inside it that are marked with one of:
+//!
+//! - `#[js_constructor]`: called by `new ClassName()`. Must return `Self` (or a Result of it).
+//! - `#[js_function(name = "...")]`: a method. Functions without a `self` receiver become
+//!   static methods on the constructor instead.
+//! - `#[js_getter(name = "...")]` and `#[js_setter(name = "...")]`: property accessors, paired
+//!   up by name. A getter without a matching setter makes a read-only property.
+//!
+//! Associated consts marked with `#[js_constant(name = "...")]` become read-only properties on
+//! the constructor, e.g. `ClassName.VERSION`.
+//!
+//! Arguments are converted with `FromJSArgs` (so anything implementing `TryConvertJSValue`,
+//! with `Option<T>` for arguments that can be left off) and return values with
+//! `TryJSValueFrom`. Functions can return a `Result` (or `EsperantoResult`), in which case an
+//! error is thrown into JS. The class name defaults to the name of the type but can be
+//! overridden with `#[js_export(name = "...")]`.
+
+mod exported_const;
+mod exported_fn;
+mod generate;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, ItemImpl, LitStr};
+
+#[proc_macro_attribute]
+pub fn js_export(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut class_name: Option<LitStr> = None;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            class_name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported js_export property"))
+        }
+    });
+    parse_macro_input!(attr with attr_parser);
+
+    let item_impl = parse_macro_input!(item as ItemImpl);
+
+    generate::export_impl(item_impl, class_name)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
@@ -7,14 +7,9 @@ macro_rules! check_jscore_exception {
             Ok(out)
         } else {
             let val: crate::jscore::jscorevaluepointer::JSCoreValuePointer = exception_val.into();
-            let js_error_convert =
-                crate::shared::value::JSValueImplementation::to_js_error(val, $ctx);
-            crate::shared::value::JSValueImplementation::release(val, $ctx);
-
-            match js_error_convert {
-                Ok(is_error) => Err(crate::EsperantoError::JavaScriptError(is_error)),
-                Err(conv_error) => Err(conv_error),
-            }
+            // The error holds on to the exception, so it needs protecting from GC
+            let retained = crate::shared::value::JSValueImplementation::retain(val, $ctx);
+            Err(crate::shared::context::error_from_exception(retained, $ctx))
         }
     }};
 }
@@ -1,4 +1,4 @@
-use std::{any::TypeId, ffi::CString};
+use std::{any::TypeId, ffi::CString, os::raw::c_char};
 
 use javascriptcore_sys::{
     JSClassCreate, JSClassDefinition, JSClassRelease, JSObjectGetPrivate, JSObjectMake,
@@ -9,7 +9,9 @@ use crate::{shared::errors::JSExportError, EsperantoResult, JSExportClass};
 
 use super::{
     jscoreexport::{
-        call_as_func_extern, constructor_extern, finalize_instance, finalize_prototype,
+        call_as_func_extern, call_getter_extern, call_method_extern, call_setter_extern,
+        constructor_extern, create_constructor, finalize_instance, finalize_native_function,
+        finalize_prototype, install_attributes, link_to_parent, native_function_extern,
     },
     jscoreruntime::JSCoreRuntimeInternal,
 };
@@ -31,6 +33,50 @@ pub(crate) struct JSClassStorage {
     // after it gets created but it seemed like it was worth keeping around in case any of this
     // implementation changes in the future
     pub(crate) prototype_class: *mut OpaqueJSClass,
+
+    // The class for the constructor object, which is linked to the prototype via the
+    // `prototype` and `constructor` properties.
+    pub(crate) constructor_class: *mut OpaqueJSClass,
+
+    // Classes for the functions created from JSExportClass::ATTRIBUTES (attached to the prototype)
+    // and JSExportClass::STATIC_ATTRIBUTES (attached to the constructor)
+    pub(crate) attribute_classes: AttributeClasses,
+    pub(crate) static_attribute_classes: AttributeClasses,
+}
+
+// The classes used for the function objects we create for each attribute. Each of these objects
+// stores the index of its attribute as private data so we know which one to call.
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub(crate) struct AttributeClasses {
+    pub(crate) method_class: *mut OpaqueJSClass,
+    pub(crate) getter_class: *mut OpaqueJSClass,
+    pub(crate) setter_class: *mut OpaqueJSClass,
+}
+
+impl AttributeClasses {
+    fn create<T: JSExportClass, const STATIC: bool>(class_name: &CString) -> Self {
+        let mut method_def = JSClassDefinition::default();
+        method_def.className = class_name.as_ptr();
+        method_def.callAsFunction = Some(call_method_extern::<T, STATIC>);
+
+        let mut getter_def = method_def;
+        getter_def.callAsFunction = Some(call_getter_extern::<T, STATIC>);
+
+        let mut setter_def = method_def;
+        setter_def.callAsFunction = Some(call_setter_extern::<T, STATIC>);
+
+        AttributeClasses {
+            method_class: unsafe { JSClassCreate(&method_def) },
+            getter_class: unsafe { JSClassCreate(&getter_def) },
+            setter_class: unsafe { JSClassCreate(&setter_def) },
+        }
+    }
+
+    fn release(&self) {
+        unsafe { JSClassRelease(self.method_class) };
+        unsafe { JSClassRelease(self.getter_class) };
+        unsafe { JSClassRelease(self.setter_class) };
+    }
 }
 
 // When we return our JSClassStorage we wrap it in this struct that also contains a pointer
@@ -104,11 +150,19 @@ impl JSClassStorage {
             prototype_def.callAsFunction = Some(call_as_func_extern::<T>);
         }
 
+        // The constructor is always callable, even if only to throw an error. Otherwise
+        // things like `instanceof` refuse to work with it.
+        let mut constructor_def = prototype_def;
+        constructor_def.callAsFunction = Some(call_as_func_extern::<T>);
+
         instance_def.finalize = Some(finalize_instance::<T>);
         prototype_def.finalize = Some(finalize_prototype::<T>);
 
         let prototype_class = unsafe { JSClassCreate(&prototype_def) };
         let instance_class = unsafe { JSClassCreate(&instance_def) };
+        let constructor_class = unsafe { JSClassCreate(&constructor_def) };
+        let attribute_classes = AttributeClasses::create::<T, false>(&name_as_c_string);
+        let static_attribute_classes = AttributeClasses::create::<T, true>(&name_as_c_string);
 
         let runtime_ref: *const JSCoreRuntimeInternal = runtime;
 
@@ -120,9 +174,23 @@ impl JSClassStorage {
             prototype,
             instance_class,
             prototype_class,
+            constructor_class,
+            attribute_classes,
+            static_attribute_classes,
         };
+
         storage_mut_ref.insert(type_id, storage.clone());
-        return Ok(JSClassStorageWithContext::new(&storage, ctx));
+        let with_context = JSClassStorageWithContext::new(&storage, ctx);
+
+        // The prototype finalizer expects to find the class in storage so we set up attributes
+        // and the constructor only after it's been stored. If this fails the prototype will be
+        // cleaned up as normal whenever it's garbage collected.
+        drop(storage_mut_ref);
+        unsafe { install_attributes::<T, false>(ctx, &storage, prototype) }?;
+        unsafe { create_constructor::<T>(ctx, &storage) }?;
+        unsafe { link_to_parent::<T>(ctx, &storage) }?;
+
+        return Ok(with_context);
     }
 
     pub(super) fn remove<T: JSExportClass>(prototype: *mut OpaqueJSValue) -> EsperantoResult<()> {
@@ -137,6 +205,30 @@ impl JSClassStorage {
 
         unsafe { JSClassRelease(stored.instance_class) };
         unsafe { JSClassRelease(stored.prototype_class) };
+        unsafe { JSClassRelease(stored.constructor_class) };
+        stored.attribute_classes.release();
+        stored.static_attribute_classes.release();
         Ok(())
     }
 }
+
+static NATIVE_FUNCTION_CLASS_NAME: &[u8] = b"Function\0";
+
+/// The class used for every function created by JSValue::new_native_function. Unlike the
+/// classes above it isn't tied to a type, so there's just the one, created on first use and
+/// released along with the runtime.
+pub(super) fn native_function_class(runtime: &JSCoreRuntimeInternal) -> *mut OpaqueJSClass {
+    let existing = runtime.native_function_class.get();
+    if !existing.is_null() {
+        return existing;
+    }
+
+    let mut definition = JSClassDefinition::default();
+    definition.className = NATIVE_FUNCTION_CLASS_NAME.as_ptr() as *const c_char;
+    definition.callAsFunction = Some(native_function_extern);
+    definition.finalize = Some(finalize_native_function);
+
+    let class = unsafe { JSClassCreate(&definition) };
+    runtime.native_function_class.set(class);
+    class
+}
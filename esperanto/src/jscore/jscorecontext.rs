@@ -1,10 +1,11 @@
 use javascriptcore_sys::{
     JSClassCreate, JSClassDefinition, JSClassRelease, JSContextGetGlobalObject, JSEvaluateScript,
-    JSGarbageCollect, JSGlobalContextCreateInGroup, JSGlobalContextRelease, OpaqueJSContext,
-    OpaqueJSValue,
+    JSGarbageCollect, JSGlobalContextCreateInGroup, JSGlobalContextRelease,
+    JSValueMakeFromJSONString, OpaqueJSContext, OpaqueJSValue,
 };
 
 use crate::shared::context::{EvaluateMetadata, JSContextError, JSContextImplementation};
+use crate::shared::errors::JavaScriptError;
 use crate::shared::value::JSValueImplementation;
 use crate::EsperantoResult;
 
@@ -70,6 +71,25 @@ impl JSContextImplementation for JSCoreContextInternal {
         Ok(wrapped.retain(self.into()))
     }
 
+    fn parse_json(self, json: &str) -> EsperantoResult<Self::ValueType> {
+        // UTF-16 rather than a C string so that NUL doesn't cut the JSON short
+        let json_utf16: Vec<u16> = json.encode_utf16().collect();
+        let mut json_jsstring = JSCoreString::from(json_utf16.as_slice());
+        let result = unsafe { JSValueMakeFromJSONString(self, json_jsstring.as_mut_raw_ptr()) };
+
+        // JSC doesn't throw when parsing fails, it just hands back null without any details.
+        if result.is_null() {
+            return Err(JavaScriptError::new(
+                "SyntaxError".to_string(),
+                "Could not parse the provided string as JSON".to_string(),
+            )
+            .into());
+        }
+
+        let wrapped = JSCoreValueInternal::from(result);
+        Ok(wrapped.retain(self.into()))
+    }
+
     fn release(self) {
         unsafe { JSGlobalContextRelease(self) }
     }
@@ -1,14 +1,24 @@
-use std::slice;
+use std::ffi::CString;
 
 use javascriptcore_sys::{
-    JSContextGetGlobalContext, JSObjectGetPrivate, JSObjectMake, JSValueMakeUndefined,
-    OpaqueJSContext, OpaqueJSValue,
+    JSContextGetGlobalContext, JSObjectCallAsFunction, JSObjectGetPrivate, JSObjectGetProperty,
+    JSObjectMake, JSObjectMakeFunction, JSObjectSetProperty, JSObjectSetPrototype,
+    JSPropertyAttributes, JSValueMakeBoolean, JSValueMakeNumber, JSValueMakeString,
+    JSValueMakeUndefined, OpaqueJSContext, OpaqueJSValue,
 };
 
 use crate::{
-    export::{JSClassFunction, JSExportPrivateData},
-    jscore::jscorevaluepointer::JSCoreValuePointer,
-    shared::errors::JSExportError,
+    export::{
+        attribute_at_index, attributes_for, JSClassFunction, JSExportAttribute, JSExportPrivateData,
+    },
+    jscore::{jscorestring::JSCoreString, jscorevaluepointer::JSCoreValuePointer},
+    shared::{
+        as_ptr::AsRawMutPtr,
+        context::{get_intrinsic, Intrinsic},
+        errors::JSExportError,
+        util::arguments_from_raw_parts,
+        value::NativeFunction,
+    },
     EsperantoResult, JSContext, JSExportClass, JSValue, Retain,
 };
 
@@ -26,6 +36,161 @@ pub(super) unsafe extern "C" fn finalize_prototype<T: JSExportClass>(val: *mut O
     JSClassStorage::remove::<T>(val).unwrap();
 }
 
+// javascriptcore-sys doesn't export the JSPropertyAttributes constants so we replicate the ones
+// we use from JSObjectRef.h
+const PROPERTY_ATTRIBUTE_READ_ONLY: JSPropertyAttributes = 1 << 1;
+const PROPERTY_ATTRIBUTE_DONT_ENUM: JSPropertyAttributes = 1 << 2;
+const PROPERTY_ATTRIBUTE_DONT_DELETE: JSPropertyAttributes = 1 << 3;
+
+unsafe fn set_property_with_attributes(
+    ctx: *mut OpaqueJSContext,
+    object: *mut OpaqueJSValue,
+    name: &CString,
+    value: *const OpaqueJSValue,
+    attributes: JSPropertyAttributes,
+) -> EsperantoResult<()> {
+    let mut name_jsstring = JSCoreString::from(name);
+    check_jscore_exception!(ctx, exception => {
+        JSObjectSetProperty(ctx, object, name_jsstring.as_mut_raw_ptr(), value, attributes, exception)
+    })
+}
+
+// The JSC C API has no way to define an accessor property directly, so we do what a script
+// would do and call (the original) Object.defineProperty().
+unsafe fn define_accessor(
+    ctx: *mut OpaqueJSContext,
+    object: *mut OpaqueJSValue,
+    name: &CString,
+    getter: *mut OpaqueJSValue,
+    setter: Option<*mut OpaqueJSValue>,
+) -> EsperantoResult<()> {
+    let define_property =
+        get_intrinsic(ctx, Intrinsic::ObjectDefineProperty)?.try_as_object(ctx)?;
+
+    let descriptor = JSObjectMake(ctx, std::ptr::null_mut(), std::ptr::null_mut());
+    set_property_with_attributes(ctx, descriptor, &CString::new("get")?, getter, 0)?;
+    if let Some(setter) = setter {
+        set_property_with_attributes(ctx, descriptor, &CString::new("set")?, setter, 0)?;
+    }
+    set_property_with_attributes(
+        ctx,
+        descriptor,
+        &CString::new("configurable")?,
+        JSValueMakeBoolean(ctx, true),
+        0,
+    )?;
+
+    let mut name_jsstring = JSCoreString::from(name);
+    let args = [
+        object as *const OpaqueJSValue,
+        JSValueMakeString(ctx, name_jsstring.as_mut_raw_ptr()),
+        descriptor,
+    ];
+
+    check_jscore_exception!(ctx, exception => {
+        JSObjectCallAsFunction(ctx, define_property, std::ptr::null_mut(), args.len(), args.as_ptr(), exception)
+    })?;
+    Ok(())
+}
+
+unsafe fn get_property_as_object(
+    ctx: *mut OpaqueJSContext,
+    object: *mut OpaqueJSValue,
+    name: &str,
+) -> EsperantoResult<*mut OpaqueJSValue> {
+    let mut name_jsstring = JSCoreString::from(&CString::new(name)?);
+    let value = check_jscore_exception!(ctx, exception => {
+        JSObjectGetProperty(ctx, object, name_jsstring.as_mut_raw_ptr(), exception)
+    })?;
+    JSCoreValuePointer::Value(value).try_as_object(ctx)
+}
+
+/// Adds everything in JSExportClass::ATTRIBUTES (or STATIC_ATTRIBUTES) to the target object.
+/// Methods, getters and setters are created as instances of their respective classes, with
+/// their index in the attribute map as private data.
+pub(super) unsafe fn install_attributes<T: JSExportClass, const STATIC: bool>(
+    ctx: *mut OpaqueJSContext,
+    storage: &JSClassStorage,
+    target: *mut OpaqueJSValue,
+) -> EsperantoResult<()> {
+    let classes = match STATIC {
+        true => &storage.static_attribute_classes,
+        false => &storage.attribute_classes,
+    };
+    let attributes = match attributes_for::<T>(STATIC) {
+        Some(attributes) => attributes,
+        None => return Ok(()),
+    };
+
+    let length_name = CString::new("length")?;
+    let name_name = CString::new("name")?;
+
+    // JSObjectMake gives objects Object.prototype, so without this the attributes wouldn't
+    // have call(), bind() etc. like the functions QuickJS creates
+    let function_prototype =
+        get_intrinsic(ctx, Intrinsic::FunctionPrototype)?.try_as_object(ctx)?;
+    let make_function = |class, index: usize| {
+        let function = JSObjectMake(ctx, class, index as _);
+        JSObjectSetPrototype(ctx, function, function_prototype);
+        function
+    };
+
+    for (index, (name, attribute)) in attributes.entries().enumerate() {
+        let name_cstring = CString::new(*name)?;
+        match attribute {
+            JSExportAttribute::Function(method) => {
+                let method_obj = make_function(classes.method_class, index);
+
+                let mut name_jsstring = JSCoreString::from(&name_cstring);
+                let name_value = JSValueMakeString(ctx, name_jsstring.as_mut_raw_ptr());
+                set_property_with_attributes(
+                    ctx,
+                    method_obj,
+                    &name_name,
+                    name_value,
+                    PROPERTY_ATTRIBUTE_READ_ONLY | PROPERTY_ATTRIBUTE_DONT_ENUM,
+                )?;
+                set_property_with_attributes(
+                    ctx,
+                    method_obj,
+                    &length_name,
+                    JSValueMakeNumber(ctx, method.num_args as f64),
+                    PROPERTY_ATTRIBUTE_READ_ONLY | PROPERTY_ATTRIBUTE_DONT_ENUM,
+                )?;
+
+                // Same as a method defined in a JS class: writable but not enumerable.
+                set_property_with_attributes(
+                    ctx,
+                    target,
+                    &name_cstring,
+                    method_obj,
+                    PROPERTY_ATTRIBUTE_DONT_ENUM,
+                )?;
+            }
+            JSExportAttribute::Property { setter, .. } => {
+                let getter_obj = make_function(classes.getter_class, index);
+                // Leaving out the setter entirely gives us a read-only accessor, which
+                // throws a TypeError when assigned to in strict mode.
+                let setter_obj = setter.map(|_| make_function(classes.setter_class, index));
+                define_accessor(ctx, target, &name_cstring, getter_obj, setter_obj)?;
+            }
+            JSExportAttribute::Constant(create_value) => {
+                let value = create_value(JSContext::borrow_from_implementation(ctx)?)?;
+                set_property_with_attributes(
+                    ctx,
+                    target,
+                    &name_cstring,
+                    value.internal.as_value(),
+                    PROPERTY_ATTRIBUTE_READ_ONLY
+                        | PROPERTY_ATTRIBUTE_DONT_ENUM
+                        | PROPERTY_ATTRIBUTE_DONT_DELETE,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
 unsafe fn execute_function<'r: 'c, 'c, T: JSExportClass, ReturnType>(
     ctx: *const OpaqueJSContext,
     argc: usize,
@@ -42,7 +207,7 @@ unsafe fn execute_function<'r: 'c, 'c, T: JSExportClass, ReturnType>(
     let result: EsperantoResult<Retain<JSValue>>;
 
     if let Some(function) = function {
-        let args: Vec<JSValue> = slice::from_raw_parts(argv, argc)
+        let args: Vec<JSValue> = arguments_from_raw_parts(argv, argc)
             .iter()
             .map(|raw| JSValue::wrap_internal(JSCoreValuePointer::Value(*raw), &context))
             .collect();
@@ -104,3 +269,318 @@ pub(super) unsafe extern "C" fn constructor_extern<'r: 'c, 'c, T: JSExportClass>
         },
     )
 }
+
+/// Creates the constructor object for a class and links it to the prototype via the
+/// `prototype` and `constructor` properties. The prototype then keeps the constructor alive.
+pub(super) unsafe fn create_constructor<T: JSExportClass>(
+    ctx: *mut OpaqueJSContext,
+    storage: &JSClassStorage,
+) -> EsperantoResult<()> {
+    let constructor = JSObjectMake(ctx, storage.constructor_class, std::ptr::null_mut());
+
+    // Make the constructor look like a regular function
+    let function_prototype =
+        get_intrinsic(ctx, Intrinsic::FunctionPrototype)?.try_as_object(ctx)?;
+    JSObjectSetPrototype(ctx, constructor, function_prototype);
+
+    let name_cstring = CString::new(T::CLASS_NAME)?;
+    let mut name_jsstring = JSCoreString::from(&name_cstring);
+    set_property_with_attributes(
+        ctx,
+        constructor,
+        &CString::new("name")?,
+        JSValueMakeString(ctx, name_jsstring.as_mut_raw_ptr()),
+        PROPERTY_ATTRIBUTE_READ_ONLY | PROPERTY_ATTRIBUTE_DONT_ENUM,
+    )?;
+
+    install_attributes::<T, true>(ctx, storage, constructor)?;
+
+    // Same attributes a JS class uses for these properties
+    set_property_with_attributes(
+        ctx,
+        constructor,
+        &CString::new("prototype")?,
+        storage.prototype,
+        PROPERTY_ATTRIBUTE_READ_ONLY
+            | PROPERTY_ATTRIBUTE_DONT_ENUM
+            | PROPERTY_ATTRIBUTE_DONT_DELETE,
+    )?;
+
+    let subclassable = make_subclassable(ctx, constructor)?;
+    set_property_with_attributes(
+        ctx,
+        storage.prototype,
+        &CString::new("constructor")?,
+        subclassable,
+        PROPERTY_ATTRIBUTE_DONT_ENUM,
+    )
+}
+
+// A JS class that extends one of ours calls our constructor via super() with itself as
+// new.target, and the instance we create should use the subclass prototype. The JSC C API
+// doesn't tell constructor callbacks what new.target is but the construct trap of a Proxy
+// does get it, so we wrap the constructor in one. Everything else passes straight through.
+// The built-ins are passed in and the handler has no prototype so that scripts replacing
+// globals or adding traps to Object.prototype can't change how our classes are constructed.
+const SUBCLASSABLE_CONSTRUCTOR_SOURCE: &str = "
+    const proxy = new Proxy(constructor, {
+        __proto__: null,
+        construct(target, args, newTarget) {
+            const instance = construct(target, args);
+            const prototype = newTarget.prototype;
+            const isObject =
+                (typeof prototype === 'object' && prototype !== null) ||
+                typeof prototype === 'function';
+            if (newTarget !== proxy && isObject) {
+                setPrototypeOf(instance, prototype);
+            }
+            return instance;
+        }
+    });
+    return proxy;
+";
+const SUBCLASSABLE_CONSTRUCTOR_ARGUMENTS: [&str; 4] =
+    ["constructor", "Proxy", "construct", "setPrototypeOf"];
+
+unsafe fn make_subclassable(
+    ctx: *mut OpaqueJSContext,
+    constructor: *mut OpaqueJSValue,
+) -> EsperantoResult<*mut OpaqueJSValue> {
+    let mut body = JSCoreString::from(&CString::new(SUBCLASSABLE_CONSTRUCTOR_SOURCE)?);
+    let mut argument_names = SUBCLASSABLE_CONSTRUCTOR_ARGUMENTS
+        .iter()
+        .map(|name| Ok(JSCoreString::from(&CString::new(*name)?)))
+        .collect::<EsperantoResult<Vec<JSCoreString>>>()?;
+    let argument_names: Vec<_> = argument_names
+        .iter_mut()
+        .map(|name| name.as_mut_raw_ptr())
+        .collect();
+
+    let create_proxy = check_jscore_exception!(ctx, exception => {
+        JSObjectMakeFunction(
+            ctx,
+            std::ptr::null_mut(),
+            argument_names.len() as u32,
+            argument_names.as_ptr(),
+            body.as_mut_raw_ptr(),
+            std::ptr::null_mut(),
+            1,
+            exception,
+        )
+    })?;
+
+    let args = [
+        constructor as *const OpaqueJSValue,
+        get_intrinsic(ctx, Intrinsic::Proxy)?.as_value(),
+        get_intrinsic(ctx, Intrinsic::ReflectConstruct)?.as_value(),
+        get_intrinsic(ctx, Intrinsic::ObjectSetPrototypeOf)?.as_value(),
+    ];
+    let proxy = check_jscore_exception!(ctx, exception => {
+        JSObjectCallAsFunction(ctx, create_proxy, std::ptr::null_mut(), args.len(), args.as_ptr(), exception)
+    })?;
+    JSCoreValuePointer::Value(proxy).try_as_object(ctx)
+}
+
+/// Subclasses get their parent's prototype and constructor in place of Object.prototype and
+/// Function.prototype, as a JS class declared with `extends` would. Has to be called after the
+/// constructor has been created.
+pub(super) unsafe fn link_to_parent<T: JSExportClass>(
+    ctx: *mut OpaqueJSContext,
+    storage: &JSClassStorage,
+) -> EsperantoResult<()> {
+    let parent = match T::PARENT {
+        Some(parent) => parent,
+        None => return Ok(()),
+    };
+
+    let context = JSContext::borrow_from_implementation(JSContextGetGlobalContext(ctx))?;
+    let parent_prototype = (parent.prototype)(context)?;
+    let parent_prototype_object = parent_prototype.internal.try_as_object(ctx)?;
+    JSObjectSetPrototype(ctx, storage.prototype, parent_prototype_object);
+
+    let constructor = get_property_as_object(ctx, storage.prototype, "constructor")?;
+    let parent_constructor = get_property_as_object(ctx, parent_prototype_object, "constructor")?;
+    JSObjectSetPrototype(ctx, constructor, parent_constructor);
+    Ok(())
+}
+
+// Methods, getters and setters all share the same setup: grab the context, wrap `this` and the
+// arguments, then look up which attribute this function object was created for.
+unsafe fn execute_attribute<T: JSExportClass, const STATIC: bool>(
+    ctx: *const OpaqueJSContext,
+    function: *mut OpaqueJSValue,
+    this_object: *mut OpaqueJSValue,
+    argc: usize,
+    argv: *const *const OpaqueJSValue,
+    exception: *mut *const OpaqueJSValue,
+    execute: for<'r, 'c, 'v> fn(
+        &'c JSContext<'r, 'c>,
+        &JSExportAttribute,
+        &'v JSValue<'r, 'c>,
+        &'v [&'v JSValue<'r, 'c>],
+    ) -> EsperantoResult<Retain<JSValue<'r, 'c>>>,
+) -> *const OpaqueJSValue {
+    let global_context = JSContextGetGlobalContext(ctx);
+    let context = JSContext::borrow_from_implementation(global_context).unwrap();
+
+    let index = JSObjectGetPrivate(function) as usize;
+    let attributes = attributes_for::<T>(STATIC);
+
+    // JSC passes a null this_object when a function is called without one
+    let this_val = match this_object.is_null() {
+        true => JSValue::wrap_internal(
+            JSCoreValuePointer::Value(JSValueMakeUndefined(ctx)),
+            context,
+        ),
+        false => JSValue::wrap_internal(JSCoreValuePointer::Object(this_object), context),
+    };
+
+    let args: Vec<JSValue> = arguments_from_raw_parts(argv, argc)
+        .iter()
+        .map(|raw| JSValue::wrap_internal(JSCoreValuePointer::Value(*raw), context))
+        .collect();
+
+    let arg_refs: Vec<&JSValue> = args.iter().collect();
+
+    let result = match attribute_at_index(&attributes, index) {
+        Some(attribute) => execute(context, attribute, &this_val, &arg_refs),
+        // Should never happen: we only ever create these functions from the attributes list.
+        None => Err(JSExportError::UnexpectedBehaviour.into()),
+    };
+
+    match result {
+        Ok(val) => val.internal.as_value(),
+        Err(error) => {
+            let error_val = JSValue::try_new_from(error, context).unwrap();
+            exception.write(error_val.internal.as_value());
+            JSValueMakeUndefined(ctx)
+        }
+    }
+}
+
+pub(super) unsafe extern "C" fn call_method_extern<T: JSExportClass, const STATIC: bool>(
+    ctx: *const OpaqueJSContext,
+    function: *mut OpaqueJSValue,
+    this_object: *mut OpaqueJSValue,
+    argc: usize,
+    argv: *const *const OpaqueJSValue,
+    exception: *mut *const OpaqueJSValue,
+) -> *const OpaqueJSValue {
+    execute_attribute::<T, STATIC>(
+        ctx,
+        function,
+        this_object,
+        argc,
+        argv,
+        exception,
+        |context, attribute, this_val, args| match attribute {
+            JSExportAttribute::Function(method) => (method.func)(context, this_val, args),
+            _ => Err(JSExportError::UnexpectedBehaviour.into()),
+        },
+    )
+}
+
+pub(super) unsafe extern "C" fn call_getter_extern<T: JSExportClass, const STATIC: bool>(
+    ctx: *const OpaqueJSContext,
+    function: *mut OpaqueJSValue,
+    this_object: *mut OpaqueJSValue,
+    argc: usize,
+    argv: *const *const OpaqueJSValue,
+    exception: *mut *const OpaqueJSValue,
+) -> *const OpaqueJSValue {
+    execute_attribute::<T, STATIC>(
+        ctx,
+        function,
+        this_object,
+        argc,
+        argv,
+        exception,
+        |context, attribute, this_val, _| match attribute {
+            JSExportAttribute::Property { getter, .. } => getter(context, this_val),
+            _ => Err(JSExportError::UnexpectedBehaviour.into()),
+        },
+    )
+}
+
+pub(super) unsafe extern "C" fn call_setter_extern<T: JSExportClass, const STATIC: bool>(
+    ctx: *const OpaqueJSContext,
+    function: *mut OpaqueJSValue,
+    this_object: *mut OpaqueJSValue,
+    argc: usize,
+    argv: *const *const OpaqueJSValue,
+    exception: *mut *const OpaqueJSValue,
+) -> *const OpaqueJSValue {
+    execute_attribute::<T, STATIC>(
+        ctx,
+        function,
+        this_object,
+        argc,
+        argv,
+        exception,
+        |context, attribute, this_val, args| match attribute {
+            JSExportAttribute::Property {
+                setter: Some(setter),
+                ..
+            } => {
+                let undefined = JSValue::undefined(context);
+                let new_value = args.first().copied().unwrap_or(&undefined);
+                setter(context, this_val, new_value)?;
+                Ok(undefined)
+            }
+            _ => Err(JSExportError::UnexpectedBehaviour.into()),
+        },
+    )
+}
+
+// Native functions store a Box<Box<NativeFunction>> as private data. The closure itself is a
+// trait object (i.e. a fat pointer) so we need the second box to get a pointer that fits.
+
+pub(super) unsafe extern "C" fn native_function_extern(
+    ctx: *const OpaqueJSContext,
+    function: *mut OpaqueJSValue,
+    this_object: *mut OpaqueJSValue,
+    argc: usize,
+    argv: *const *const OpaqueJSValue,
+    exception: *mut *const OpaqueJSValue,
+) -> *const OpaqueJSValue {
+    let global_context = JSContextGetGlobalContext(ctx);
+    let context = JSContext::borrow_from_implementation(global_context).unwrap();
+
+    let func = (JSObjectGetPrivate(function) as *const Box<NativeFunction>).as_ref();
+
+    let this_val = match this_object.is_null() {
+        true => JSValue::wrap_internal(
+            JSCoreValuePointer::Value(JSValueMakeUndefined(ctx)),
+            context,
+        ),
+        false => JSValue::wrap_internal(JSCoreValuePointer::Object(this_object), context),
+    };
+
+    let args: Vec<JSValue> = arguments_from_raw_parts(argv, argc)
+        .iter()
+        .map(|raw| JSValue::wrap_internal(JSCoreValuePointer::Value(*raw), context))
+        .collect();
+
+    let arg_refs: Vec<&JSValue> = args.iter().collect();
+
+    let result = match func {
+        Some(func) => func(context, &this_val, &arg_refs),
+        None => Err(JSExportError::UnexpectedBehaviour.into()),
+    };
+
+    match result {
+        Ok(val) => val.internal.as_value(),
+        Err(error) => {
+            let error_val = JSValue::try_new_from(error, context).unwrap();
+            exception.write(error_val.internal.as_value());
+            JSValueMakeUndefined(ctx)
+        }
+    }
+}
+
+pub(super) unsafe extern "C" fn finalize_native_function(val: *mut OpaqueJSValue) {
+    let ptr = JSObjectGetPrivate(val) as *mut Box<NativeFunction>;
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr));
+    }
+}
@@ -1,6 +1,14 @@
-use std::{any::TypeId, cell::RefCell, collections::HashMap, ops::Deref};
-
-use javascriptcore_sys::{JSContextGroupCreate, JSContextGroupRelease, OpaqueJSContextGroup};
+use std::{
+    any::TypeId,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ops::Deref,
+};
+
+use javascriptcore_sys::{
+    JSClassRelease, JSContextGroupCreate, JSContextGroupRelease, OpaqueJSClass,
+    OpaqueJSContextGroup,
+};
 
 use crate::shared::runtime::{JSRuntimeError, JSRuntimeImplementation};
 
@@ -10,6 +18,9 @@ use super::jscore_class_storage::JSClassStorage;
 pub(crate) struct JSCoreRuntimeInternal {
     pub(super) raw: *const OpaqueJSContextGroup,
     pub(super) class_storage: RefCell<HashMap<TypeId, JSClassStorage>>,
+    // Shared by every function made with JSValue::new_native_function. Null until the
+    // first one is created.
+    pub(super) native_function_class: Cell<*mut OpaqueJSClass>,
 }
 
 // pub type JSCoreRuntimeInternal = *const OpaqueJSContextGroup;
@@ -24,11 +35,17 @@ impl JSRuntimeImplementation for JSCoreRuntimeInternal {
         Ok(JSCoreRuntimeInternal {
             raw,
             class_storage: RefCell::new(HashMap::new()),
+            native_function_class: Cell::new(std::ptr::null_mut()),
         })
     }
 
     fn release(&mut self) {
         unsafe { JSContextGroupRelease(self.raw) }
+        // Any functions still alive retain the class themselves
+        let native_function_class = self.native_function_class.replace(std::ptr::null_mut());
+        if !native_function_class.is_null() {
+            unsafe { JSClassRelease(native_function_class) }
+        }
         #[cfg(debug_assertions)]
         {
             assert_eq!(self.class_storage.borrow().len(), 0)
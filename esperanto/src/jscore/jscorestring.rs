@@ -7,8 +7,9 @@ use std::{
 };
 
 use javascriptcore_sys::{
-    JSStringCreateWithUTF8CString, JSStringGetMaximumUTF8CStringSize, JSStringGetUTF8CString,
-    JSStringRelease, OpaqueJSString,
+    JSStringCreateWithCharacters, JSStringCreateWithUTF8CString, JSStringGetCharactersPtr,
+    JSStringGetLength, JSStringGetMaximumUTF8CStringSize, JSStringGetUTF8CString, JSStringRelease,
+    OpaqueJSString,
 };
 
 use crate::shared::{as_ptr::AsRawMutPtr, errors::ConversionError};
@@ -84,6 +85,26 @@ impl<'a> From<&CString> for JSCoreString<'a> {
     }
 }
 
+impl<'a> From<&[u16]> for JSCoreString<'a> {
+    fn from(value: &[u16]) -> Self {
+        let ptr = unsafe { JSStringCreateWithCharacters(value.as_ptr(), value.len()) };
+        JSCoreString::from_retained_ptr(ptr)
+    }
+}
+
+impl<'a> From<JSCoreString<'a>> for Vec<u16> {
+    fn from(mut value: JSCoreString<'a>) -> Self {
+        let value_ptr = value.as_mut_raw_ptr();
+        let len = unsafe { JSStringGetLength(value_ptr) };
+        if len == 0 {
+            // The characters pointer isn't guaranteed to be valid for an empty string
+            return Vec::new();
+        }
+        let chars = unsafe { std::slice::from_raw_parts(JSStringGetCharactersPtr(value_ptr), len) };
+        chars.to_vec()
+    }
+}
+
 impl<'a> TryFrom<JSCoreString<'a>> for CString {
     type Error = ConversionError;
 
@@ -122,4 +143,11 @@ mod test {
         let string = CString::try_from(str).unwrap();
         assert_eq!(string.to_str().unwrap(), "hello");
     }
+
+    #[test]
+    fn round_trips_utf16() {
+        let units = vec![0x61, 0x0, 0xD83D];
+        let str = JSCoreString::from(units.as_slice());
+        assert_eq!(Vec::<u16>::from(str), units);
+    }
 }
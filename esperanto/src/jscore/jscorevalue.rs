@@ -5,33 +5,79 @@ use std::{
 };
 
 use javascriptcore_sys::{
-    JSObjectCallAsConstructor, JSObjectCallAsFunction, JSObjectDeleteProperty, JSObjectGetPrivate,
-    JSObjectGetProperty, JSObjectMake, JSObjectMakeError, JSObjectMakeFunction, JSObjectSetPrivate,
-    JSObjectSetProperty, JSObjectSetPrototype, JSValueIsInstanceOfConstructor, JSValueIsObject,
-    JSValueIsStrictEqual, JSValueIsString, JSValueMakeBoolean, JSValueMakeNumber,
-    JSValueMakeString, JSValueMakeUndefined, JSValueProtect, JSValueToBoolean, JSValueToNumber,
-    JSValueToStringCopy, JSValueUnprotect, OpaqueJSContext, OpaqueJSString, OpaqueJSValue,
+    JSObjectCallAsConstructor, JSObjectCallAsFunction, JSObjectCopyPropertyNames,
+    JSObjectDeleteProperty, JSObjectGetPrivate, JSObjectGetProperty, JSObjectGetPropertyAtIndex,
+    JSObjectIsFunction, JSObjectMake, JSObjectMakeArray, JSObjectMakeError, JSObjectMakeFunction,
+    JSObjectSetPrivate, JSObjectSetProperty, JSObjectSetPropertyAtIndex, JSObjectSetPrototype,
+    JSPropertyAttributes, JSPropertyNameArrayGetCount, JSPropertyNameArrayGetNameAtIndex,
+    JSPropertyNameArrayRelease, JSStringRetain, JSValueCreateJSONString, JSValueIsArray,
+    JSValueIsBoolean, JSValueIsInstanceOfConstructor, JSValueIsNull, JSValueIsNumber,
+    JSValueIsObject, JSValueIsObjectOfClass, JSValueIsStrictEqual, JSValueIsString,
+    JSValueIsUndefined, JSValueMakeBoolean, JSValueMakeNull, JSValueMakeNumber, JSValueMakeString,
+    JSValueMakeUndefined, JSValueProtect, JSValueToBoolean, JSValueToNumber, JSValueToStringCopy,
+    JSValueUnprotect, OpaqueJSContext, OpaqueJSString, OpaqueJSValue,
 };
 
 use crate::{
     export::JSExportPrivateData,
     shared::{
-        context::JSContextImplementation,
-        errors::EsperantoResult,
-        value::{JSValueError, JSValueImplementation},
+        context::{get_intrinsic, Intrinsic, JSContextImplementation},
+        errors::{EsperantoResult, JSExportError},
+        value::{ErrorLocation, JSValueError, JSValueImplementation, NativeFunction, PropertyKey},
     },
     JSExportClass,
 };
 
 use crate::shared::as_ptr::AsRawMutPtr;
+use crate::shared::util::{utf16_from_wtf8, wtf8_from_utf16};
 
 use super::{
-    jscore_class_storage::JSClassStorage, jscorestring::JSCoreString,
+    jscore_class_storage::{native_function_class, JSClassStorage},
+    jscorestring::JSCoreString,
     jscorevaluepointer::JSCoreValuePointer,
 };
 
 pub(crate) type JSCoreValueInternal = JSCoreValuePointer;
 
+// Symbol support arrived in a newer version of the JSC API than the one javascriptcore-sys
+// binds against, so we declare what we need ourselves. The framework is already linked.
+extern "C" {
+    fn JSValueIsSymbol(ctx: *const OpaqueJSContext, value: *const OpaqueJSValue) -> bool;
+    fn JSValueMakeSymbol(
+        ctx: *const OpaqueJSContext,
+        description: *mut OpaqueJSString,
+    ) -> *const OpaqueJSValue;
+    fn JSObjectGetPropertyForKey(
+        ctx: *const OpaqueJSContext,
+        object: *mut OpaqueJSValue,
+        property_key: *const OpaqueJSValue,
+        exception: *mut *const OpaqueJSValue,
+    ) -> *const OpaqueJSValue;
+    fn JSObjectSetPropertyForKey(
+        ctx: *const OpaqueJSContext,
+        object: *mut OpaqueJSValue,
+        property_key: *const OpaqueJSValue,
+        value: *const OpaqueJSValue,
+        attributes: JSPropertyAttributes,
+        exception: *mut *const OpaqueJSValue,
+    );
+    fn JSObjectDeletePropertyForKey(
+        ctx: *const OpaqueJSContext,
+        object: *mut OpaqueJSValue,
+        property_key: *const OpaqueJSValue,
+        exception: *mut *const OpaqueJSValue,
+    ) -> bool;
+}
+
+// Strings are passed to JSCoreString as UTF-16 so that they can contain NUL
+fn encode_utf16(value: &str) -> Vec<u16> {
+    value.encode_utf16().collect()
+}
+
+static SOURCE_URL_STRING: &[u8] = b"sourceURL\0";
+static LINE_STRING: &[u8] = b"line\0";
+static COLUMN_STRING: &[u8] = b"column\0";
+
 // static CONSTRUCTOR_STRING: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"constructor\0") };
 
 impl JSValueImplementation for JSCoreValueInternal {
@@ -55,12 +101,54 @@ impl JSValueImplementation for JSCoreValueInternal {
         Ok(jsc_string.try_into()?)
     }
 
+    fn to_json_string(
+        self,
+        indent: u32,
+        ctx: Self::ContextType,
+    ) -> EsperantoResult<Option<CString>> {
+        let ptr = check_jscore_exception!(ctx, exception => {
+            unsafe { JSValueCreateJSONString(ctx, self.as_value(), indent, exception) }
+        })?;
+
+        // No exception but no string either means the value can't be represented in JSON
+        if ptr.is_null() {
+            return Ok(None);
+        }
+
+        let jsc_string = JSCoreString::from_retained_ptr(ptr);
+        Ok(Some(jsc_string.try_into()?))
+    }
+
     fn from_cstring(value: &CString, ctx: Self::ContextType) -> Self {
         let mut js_string = JSCoreString::from(value);
         let ptr = unsafe { JSValueMakeString(ctx, js_string.as_mut_raw_ptr()) };
         ptr.into()
     }
 
+    fn as_utf16(self, ctx: Self::ContextType) -> EsperantoResult<Vec<u16>> {
+        let ptr = check_jscore_exception!(ctx, exception => {
+            unsafe { JSValueToStringCopy(ctx, self.as_value(), exception) }
+        })?;
+
+        Ok(JSCoreString::from_retained_ptr(ptr).into())
+    }
+
+    fn from_utf16(value: &[u16], ctx: Self::ContextType) -> EsperantoResult<Self> {
+        let mut js_string = JSCoreString::from(value);
+        let ptr = unsafe { JSValueMakeString(ctx, js_string.as_mut_raw_ptr()) };
+        Ok(ptr.into())
+    }
+
+    // JavaScriptCore strings are UTF-16 internally, so WTF-8 is converted on our side
+
+    fn as_wtf8(self, ctx: Self::ContextType) -> EsperantoResult<Vec<u8>> {
+        Ok(wtf8_from_utf16(&self.as_utf16(ctx)?))
+    }
+
+    fn from_wtf8(value: &[u8], ctx: Self::ContextType) -> EsperantoResult<Self> {
+        Self::from_utf16(&utf16_from_wtf8(value), ctx)
+    }
+
     fn as_number(self, ctx: Self::ContextType) -> EsperantoResult<f64> {
         check_jscore_exception!(ctx, exception => {
             unsafe { JSValueToNumber(ctx, self.as_value(), exception) }
@@ -81,6 +169,30 @@ impl JSValueImplementation for JSCoreValueInternal {
         Ok(unsafe { JSValueToBoolean(ctx, self.as_value()) })
     }
 
+    fn is_bigint(self, ctx: Self::ContextType) -> bool {
+        // The version of the JSC API we bind against predates BigInt, so JSValueGetType can't
+        // identify one. Instead we rule out every other type.
+        let value = self.as_value();
+        unsafe {
+            (JSValueIsUndefined(ctx, value)
+                || JSValueIsNull(ctx, value)
+                || JSValueIsBoolean(ctx, value)
+                || JSValueIsNumber(ctx, value)
+                || JSValueIsString(ctx, value)
+                || JSValueIsSymbol(ctx, value)
+                || JSValueIsObject(ctx, value))
+                == false
+        }
+    }
+
+    fn from_i64_bigint(value: i64, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        make_bigint(value.to_string(), ctx)
+    }
+
+    fn from_u64_bigint(value: u64, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        make_bigint(value.to_string(), ctx)
+    }
+
     fn set_property(
         self,
         ctx: Self::ContextType,
@@ -109,6 +221,32 @@ impl JSValueImplementation for JSCoreValueInternal {
         Ok(result.into())
     }
 
+    fn new_native_function(
+        func: Box<NativeFunction>,
+        ctx: Self::ContextType,
+        runtime: &<Self::ContextType as JSContextImplementation>::RuntimeType,
+    ) -> EsperantoResult<Self> {
+        let class = native_function_class(runtime);
+        let private_data = Box::into_raw(Box::new(func));
+        let raw = unsafe { JSObjectMake(ctx, class, private_data as _) };
+
+        // Same as from_native_class, this isn't retained by default
+        unsafe { JSValueProtect(ctx, raw) }
+
+        // Give it Function.prototype so that call(), bind() etc. work as expected
+        let function_prototype = get_intrinsic(ctx, Intrinsic::FunctionPrototype);
+
+        match function_prototype {
+            Ok(proto) => unsafe { JSObjectSetPrototype(ctx, raw, proto.as_value()) },
+            Err(err) => {
+                JSCoreValuePointer::Object(raw).release(ctx);
+                return Err(err);
+            }
+        }
+
+        Ok(JSCoreValuePointer::Object(raw))
+    }
+
     fn from_native_class<T: JSExportClass>(
         instance: T,
         ctx: Self::ContextType,
@@ -257,6 +395,50 @@ impl JSValueImplementation for JSCoreValueInternal {
         unsafe { JSValueMakeUndefined(ctx) }.into()
     }
 
+    fn null(ctx: Self::ContextType) -> Self {
+        unsafe { JSValueMakeNull(ctx) }.into()
+    }
+
+    fn is_undefined(self, ctx: Self::ContextType) -> bool {
+        unsafe { JSValueIsUndefined(ctx, self.as_value()) }
+    }
+
+    fn is_null(self, ctx: Self::ContextType) -> bool {
+        unsafe { JSValueIsNull(ctx, self.as_value()) }
+    }
+
+    fn is_bool(self, ctx: Self::ContextType) -> bool {
+        unsafe { JSValueIsBoolean(ctx, self.as_value()) }
+    }
+
+    fn is_number(self, ctx: Self::ContextType) -> bool {
+        unsafe { JSValueIsNumber(ctx, self.as_value()) }
+    }
+
+    fn is_function(self, ctx: Self::ContextType) -> bool {
+        match self.try_as_object(ctx) {
+            Ok(obj) => unsafe { JSObjectIsFunction(ctx, obj) },
+            Err(_) => false,
+        }
+    }
+
+    fn is_symbol(self, ctx: Self::ContextType) -> bool {
+        unsafe { JSValueIsSymbol(ctx, self.as_value()) }
+    }
+
+    fn new_symbol(description: Option<&str>, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        let mut description =
+            description.map(|description| JSCoreString::from(encode_utf16(description).as_slice()));
+        let description_ptr = match description.as_mut() {
+            Some(description) => description.as_mut_raw_ptr(),
+            // Same as calling Symbol() with no arguments
+            None => std::ptr::null_mut(),
+        };
+        let symbol: Self = unsafe { JSValueMakeSymbol(ctx, description_ptr) }.into();
+        // Unlike numbers and booleans symbols are garbage collected, so protect it like an object
+        Ok(symbol.retain(ctx))
+    }
+
     fn native_prototype_for<'r: 'c, 'c, T: JSExportClass>(
         ctx: Self::ContextType,
         runtime: &<Self::ContextType as JSContextImplementation>::RuntimeType,
@@ -273,6 +455,10 @@ impl JSValueImplementation for JSCoreValueInternal {
     //     Ok(constructor)
     // }
 
+    fn intrinsic(intrinsic: Intrinsic, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        get_intrinsic(ctx, intrinsic)
+    }
+
     fn equals(self, other: Self, ctx: Self::ContextType) -> bool {
         unsafe { JSValueIsStrictEqual(ctx, self.as_value(), other.as_value()) }
     }
@@ -283,18 +469,178 @@ impl JSValueImplementation for JSCoreValueInternal {
         })
     }
 
-    fn get_native_ref<'a, T: JSExportClass>(
+    fn get_native_ptr<T: JSExportClass>(
         self,
         ctx: Self::ContextType,
-    ) -> EsperantoResult<&'a T> {
-        let ptr = unsafe { JSObjectGetPrivate(self.try_as_object(ctx)?) };
-        JSExportPrivateData::data_from_ptr(ptr)
+        runtime: &<Self::ContextType as JSContextImplementation>::RuntimeType,
+    ) -> EsperantoResult<*mut c_void> {
+        // Attribute functions, prototypes and native functions all have private data too, but
+        // only instances of our exported classes hold a JSExportPrivateData. So we make sure
+        // the object was created with one of those classes before handing the pointer out.
+        // JSExportPrivateData then checks whether it's actually a T (or a subclass of T).
+        let object = self.try_as_object(ctx)?;
+        let is_exported_instance = runtime
+            .class_storage
+            .borrow()
+            .values()
+            .any(|storage| unsafe { JSValueIsObjectOfClass(ctx, object, storage.instance_class) });
+
+        if is_exported_instance == false {
+            return Err(JSExportError::CouldNotGetNativeObject(T::CLASS_NAME).into());
+        }
+        Ok(unsafe { JSObjectGetPrivate(object) })
     }
 
-    fn delete_property(self, ctx: Self::ContextType, name: &CStr) -> EsperantoResult<bool> {
-        let mut name_jsstring = JSCoreString::from(name);
+    fn get_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+    ) -> EsperantoResult<Self> {
+        let object = self.try_as_object(ctx)?;
+        let result = match key {
+            PropertyKey::Index(index) => return self.get_property_at_index(ctx, index),
+            PropertyKey::Name(name) => {
+                let mut name_jsstring = JSCoreString::from(encode_utf16(name).as_slice());
+                check_jscore_exception!(ctx, exception => {
+                    unsafe { JSObjectGetProperty(ctx, object, name_jsstring.as_mut_raw_ptr(), exception) }
+                })?
+            }
+            PropertyKey::Symbol(value) => check_jscore_exception!(ctx, exception => {
+                unsafe { JSObjectGetPropertyForKey(ctx, object, value.internal.as_value(), exception) }
+            })?,
+        };
+
+        Ok(result.into())
+    }
+
+    fn set_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+        new_value: Self,
+    ) -> EsperantoResult<()> {
+        let object = self.try_as_object(ctx)?;
+        match key {
+            PropertyKey::Index(index) => self.set_property_at_index(ctx, index, new_value),
+            PropertyKey::Name(name) => {
+                let mut name_jsstring = JSCoreString::from(encode_utf16(name).as_slice());
+                check_jscore_exception!(ctx, exception => {
+                    unsafe { JSObjectSetProperty(ctx, object, name_jsstring.as_mut_raw_ptr(), new_value.as_value(), 0, exception) }
+                })
+            }
+            PropertyKey::Symbol(value) => check_jscore_exception!(ctx, exception => {
+                unsafe { JSObjectSetPropertyForKey(ctx, object, value.internal.as_value(), new_value.as_value(), 0, exception) }
+            }),
+        }
+    }
+
+    fn delete_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+    ) -> EsperantoResult<bool> {
+        let object = self.try_as_object(ctx)?;
+        match key {
+            PropertyKey::Name(name) => {
+                let mut name_jsstring = JSCoreString::from(encode_utf16(name).as_slice());
+                check_jscore_exception!(ctx, exception => {
+                    unsafe { JSObjectDeleteProperty(ctx, object, name_jsstring.as_mut_raw_ptr(), exception) }
+                })
+            }
+            // There's no index-based delete, but the ForKey version accepts a number
+            PropertyKey::Index(index) => {
+                let index_value = Self::from_number(index as f64, ctx)?;
+                check_jscore_exception!(ctx, exception => {
+                    unsafe { JSObjectDeletePropertyForKey(ctx, object, index_value.as_value(), exception) }
+                })
+            }
+            PropertyKey::Symbol(value) => check_jscore_exception!(ctx, exception => {
+                unsafe { JSObjectDeletePropertyForKey(ctx, object, value.internal.as_value(), exception) }
+            }),
+        }
+    }
+
+    fn new_object(ctx: Self::ContextType) -> EsperantoResult<Self> {
+        let raw = unsafe { JSObjectMake(ctx, std::ptr::null_mut(), std::ptr::null_mut()) };
+        let as_internal: Self = raw.into();
+        Ok(as_internal.retain(ctx))
+    }
+
+    fn own_property_names(self, ctx: Self::ContextType) -> EsperantoResult<Vec<Vec<u8>>> {
+        // JSObjectCopyPropertyNames also returns enumerable properties inherited from the
+        // prototype chain, so we check each one with the original hasOwnProperty to end up
+        // with the same names as QuickJS.
+        let has_own_property = get_intrinsic(ctx, Intrinsic::ObjectHasOwnProperty)?;
+        let names = unsafe { JSObjectCopyPropertyNames(ctx, self.try_as_object(ctx)?) };
+        let count = unsafe { JSPropertyNameArrayGetCount(names) };
+
+        let result = (0..count)
+            .filter_map(|index| {
+                let name = unsafe { JSPropertyNameArrayGetNameAtIndex(names, index) };
+                let name_value: Self = unsafe { JSValueMakeString(ctx, name) }.into();
+                let is_own = has_own_property
+                    .call_as_function(vec![name_value], Some(self), ctx)
+                    .and_then(|result| {
+                        let is_own = result.as_bool(ctx);
+                        result.release(ctx);
+                        is_own
+                    });
+                match is_own {
+                    Ok(true) => {
+                        // The array owns its strings, so we need a retain of our own for
+                        // JSCoreString to release when it's done
+                        let name = JSCoreString::from_retained_ptr(unsafe { JSStringRetain(name) });
+                        Some(Ok(wtf8_from_utf16(&Vec::<u16>::from(name))))
+                    }
+                    Ok(false) => None,
+                    Err(err) => Some(Err(err)),
+                }
+            })
+            .collect();
+
+        unsafe { JSPropertyNameArrayRelease(names) };
+        result
+    }
+
+    fn new_array(values: Vec<Self>, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        let value_ptrs: Vec<*const OpaqueJSValue> = values.iter().map(|v| v.as_value()).collect();
+        let raw = check_jscore_exception!(ctx, exception => {
+            unsafe { JSObjectMakeArray(ctx, value_ptrs.len(), value_ptrs.as_ptr(), exception) }
+        })?;
+
+        // Another "Make" function, so it isn't retained for us
+        let as_internal: Self = raw.into();
+        Ok(as_internal.retain(ctx))
+    }
+
+    fn is_array(self, ctx: Self::ContextType) -> EsperantoResult<bool> {
+        Ok(unsafe { JSValueIsArray(ctx, self.as_value()) })
+    }
+
+    fn get_property_at_index(self, ctx: Self::ContextType, index: u32) -> EsperantoResult<Self> {
+        let result = check_jscore_exception!(ctx, exception => {
+            unsafe { JSObjectGetPropertyAtIndex(ctx, self.try_as_object(ctx)?, index, exception) }
+        })?;
+
+        Ok(result.into())
+    }
+
+    fn set_property_at_index(
+        self,
+        ctx: Self::ContextType,
+        index: u32,
+        new_value: Self,
+    ) -> EsperantoResult<()> {
         check_jscore_exception!(ctx, exception => {
-            unsafe { JSObjectDeleteProperty(ctx, self.try_as_object(ctx)?, name_jsstring.as_mut(), exception)}
+            unsafe {
+                JSObjectSetPropertyAtIndex(
+                    ctx,
+                    self.try_as_object(ctx)?,
+                    index,
+                    new_value.as_value(),
+                    exception,
+                )
+            }
         })
     }
 
@@ -308,6 +654,15 @@ impl JSValueImplementation for JSCoreValueInternal {
         self.is_instanceof(error_type, ctx)
     }
 
+    fn error_location(self, ctx: Self::ContextType) -> EsperantoResult<ErrorLocation> {
+        let property_name = |bytes: &'static [u8]| CStr::from_bytes_with_nul(bytes).unwrap();
+        Ok(ErrorLocation {
+            source_url: self.get_optional_string_property(ctx, property_name(SOURCE_URL_STRING))?,
+            line: self.get_optional_position_property(ctx, property_name(LINE_STRING))?,
+            column: self.get_optional_position_property(ctx, property_name(COLUMN_STRING))?,
+        })
+    }
+
     fn get_private_data(self, ctx: Self::ContextType) -> EsperantoResult<*mut c_void> {
         let as_obj = self.try_as_object(ctx)?;
         Ok(unsafe { JSObjectGetPrivate(as_obj) })
@@ -322,6 +677,13 @@ impl JSValueImplementation for JSCoreValueInternal {
     }
 }
 
+// No BigInt functions in the API either, so we call the original BigInt() with the digits instead
+fn make_bigint(digits: String, ctx: *mut OpaqueJSContext) -> EsperantoResult<JSCoreValueInternal> {
+    let constructor = get_intrinsic(ctx, Intrinsic::BigInt)?;
+    let digits = JSCoreValueInternal::from_cstring(&CString::new(digits)?, ctx);
+    constructor.call_as_function(vec![digits], None, ctx)
+}
+
 // impl TryFromInJSContext<i32> for JSCoreValuePointer {
 //     fn from_in_context(value: &i32, in_context: &JSCoreContextPointer) -> EsperantoResult<Self> {
 //         let ptr = unsafe { JSValueMakeNumber(in_context.into(), *value as f64) };
@@ -11,7 +11,10 @@ pub use shared::errors::{EsperantoError, EsperantoResult};
 pub use shared::export::JSExportClass;
 pub use shared::retain::Retain;
 pub use shared::runtime::JSRuntime;
-pub use shared::value::{AsJSValueRef, JSValue, JSValueFrom, TryConvertJSValue, TryJSValueFrom};
+pub use shared::value::{
+    AsJSValueRef, FromJSArgs, JSValue, JSValueFrom, JSValueIterator, JSValueType, Nullable,
+    PropertyKey, Strict, TryConvertJSValue, TryJSValueFrom, WellKnownSymbol,
+};
 
 pub mod errors {
     pub use super::shared::errors::*;
@@ -20,4 +23,16 @@ pub mod errors {
 
 pub mod export {
     pub use super::shared::export::*;
+    pub use esperanto_macro::js_export;
+
+    // Used by code generated by js_export
+    #[doc(hidden)]
+    pub use phf;
+}
+
+#[cfg(feature = "serde")]
+pub mod serde {
+    pub use super::shared::serialization::{
+        from_value, to_value, JSValueDeserializer, JSValueSerializer,
+    };
 }
@@ -1,19 +1,51 @@
+use std::os::raw::c_char;
+
+use quickjs_android_suitable_sys::{
+    JSAtom, JSValue as QuickJSValue, JS_IsException__, JS_ATOM_NULL,
+};
+
+/// QuickJS reports that a call threw through its return value, and each return type has its
+/// own way of doing it. We can't use the pending exception for this because anything can be
+/// thrown, including null, which is also what JS_GetException returns when there isn't one.
+pub(crate) trait QuickJSReturnValue {
+    fn is_exception(&self) -> bool;
+}
+
+impl QuickJSReturnValue for QuickJSValue {
+    fn is_exception(&self) -> bool {
+        unsafe { JS_IsException__(*self) == 1 }
+    }
+}
+
+// Functions returning a status (e.g. JS_SetPropertyStr) or a boolean (e.g. JS_IsArray)
+impl QuickJSReturnValue for i32 {
+    fn is_exception(&self) -> bool {
+        *self < 0
+    }
+}
+
+impl QuickJSReturnValue for JSAtom {
+    fn is_exception(&self) -> bool {
+        *self == JS_ATOM_NULL
+    }
+}
+
+impl QuickJSReturnValue for *const c_char {
+    fn is_exception(&self) -> bool {
+        self.is_null()
+    }
+}
+
 macro_rules! check_quickjs_exception {
     ($ctx:expr => $stmt:expr) => {{
         let result = $stmt;
-        let exception = unsafe { quickjs_android_suitable_sys::JS_GetException(*$ctx) };
-        if unsafe { quickjs_android_suitable_sys::JS_IsError(*$ctx, exception) } == 0 {
+        if !crate::quickjs::exception::QuickJSReturnValue::is_exception(&result) {
             Ok(result)
         } else {
-            let js_error_convert =
-                crate::shared::value::JSValueInternal::to_js_error(exception, $ctx);
-
-            crate::shared::value::JSValueInternal::release(exception, $ctx);
-
-            match js_error_convert {
-                Ok(is_error) => Err(crate::EsperantoError::JavaScriptError(is_error)),
-                Err(conv_error) => Err(conv_error),
-            }
+            let exception = unsafe { quickjs_android_suitable_sys::JS_GetException(*$ctx) };
+            Err(crate::shared::context::error_from_exception(
+                exception, $ctx,
+            ))
         }
     }};
 }
@@ -9,8 +9,7 @@ mod quickjscontextpointer;
 mod quickjsruntime;
 mod quickjsvalue;
 
-pub(crate) use quickjscontext::QuickJSContextInternal as JSContextInternalImpl;
-pub(crate) use quickjsexport::QuickJSClassID as JSClassIdentifierImpl;
+pub(crate) use quickjscontext::QuickJSContextInternal as ActiveJSContextImplementation;
 pub(crate) use quickjsruntime::QuickJSRuntimeInternal as JSRuntimeInternalImpl;
 pub(crate) use quickjsvalue::QuickJSValueInternal as JSValueInternalImpl;
 // pub mod  export {
@@ -6,30 +6,35 @@
  *
  * We take advantage of QuickJS providing opaque storage for runtimes (something JSC
  * doesn't provide, sadly) to store all this alongside the runtime itself.
+ *
+ * Class definitions belong to the runtime and live as long as it does, so the IDs are only
+ * cleared once the runtime is freed. Prototypes, on the other hand, belong to individual
+ * contexts and are created the first time a class is used in each one.
  */
-use std::{any::TypeId, collections::HashMap};
+use std::{any::TypeId, collections::HashMap, ffi::c_void};
 
 use quickjs_android_suitable_sys::{
-    JS_GetRuntimeOpaque, JS_NewClassID, JS_SetClassProto, JS_SetRuntimeOpaque,
+    JSValue as QuickJSValue, JS_FreeValue__, JS_GetClassProto, JS_GetOpaque, JS_GetRuntimeOpaque,
+    JS_GetTag__, JS_NewClassID, JS_SetClassProto, JS_SetRuntimeOpaque, JS_TAG_NULL,
 };
 
 use crate::{
     quickjs::quickjscontext::QuickJSContextInternal,
     quickjs::quickjsexport::QuickJSExportExtensions,
-    quickjs::quickjsruntime::QuickJSRuntimeInternal,
-    shared::{context::JSContextInternal, errors::JSExportError},
+    quickjs::quickjsruntime::QuickJSRuntimeInternal, shared::errors::JSExportError,
     EsperantoResult, JSExportClass,
 };
 
 // A simple struct to wrap our class IDs. We need to create separate classes for both
 // prototypes (where methods, constructors etc are defined) and instances (where we store
 // the raw pointers to our Rust structs)
+#[derive(Clone, Copy)]
 struct StoredClassIDs {
     instance: u32,
-    // We actually don't ever refer back to the prototype ID after initial use but it feels
-    // worth storing in case we do sometime in the future (store private data in the prototype?
-    // we might!)
-    _prototype: u32,
+    // We need the prototype and constructor IDs whenever the class is used in a new context,
+    // since each context gets its own prototype and constructor objects.
+    prototype: u32,
+    constructor: u32,
 }
 
 type ClassIDStorage = HashMap<TypeId, StoredClassIDs>;
@@ -41,55 +46,62 @@ fn get_new_class_id() -> u32 {
 }
 
 fn create_ids<T: JSExportClass>(
-    context: QuickJSContextInternal,
     runtime: QuickJSRuntimeInternal,
 ) -> EsperantoResult<StoredClassIDs> {
     let prototype_class_id = get_new_class_id();
     let instance_class_id = get_new_class_id();
+    let constructor_class_id = get_new_class_id();
 
-    T::create_prototype_class(*context, prototype_class_id)?;
+    T::create_prototype_class(runtime, prototype_class_id)?;
     T::create_instance_class(runtime, instance_class_id)?;
-
-    let prototype = T::create_prototype(*context, prototype_class_id);
-
-    // Rather than have to specify the prototype each time QuickJS lets us set a class
-    // prototype, which it then automatically uses. This means we don't have to keep
-    // track of prototype objects ourselves. Which is nice.
-    unsafe { JS_SetClassProto(*context, instance_class_id, prototype) };
+    T::create_constructor_class(runtime, constructor_class_id)?;
 
     Ok(StoredClassIDs {
         instance: instance_class_id,
-        _prototype: prototype_class_id,
+        prototype: prototype_class_id,
+        constructor: constructor_class_id,
     })
 }
 
-pub(super) fn clear_class<T: JSExportClass>(runtime: QuickJSRuntimeInternal) {
-    let storage_ptr = unsafe { JS_GetRuntimeOpaque(runtime) } as *mut ClassIDStorage;
-    if storage_ptr.is_null() {
-        // clear_class() being called implies we've *defined* a custom class. If we've defined
-        // the class but it isn't stored anywhere we're in undefined behaviour territory. Not a lot
-        // we can do here since clear_class is called by QuickJS itself. So we should panic:
-        panic!("Tried to clear class but there's no storage present");
+fn ensure_prototype_exists<T: JSExportClass>(
+    context: QuickJSContextInternal,
+    ids: &StoredClassIDs,
+) -> EsperantoResult<()> {
+    // QuickJS fills in the class prototype for every context with null until one is set, so
+    // that's how we know this context hasn't seen the class before.
+    let existing = unsafe { JS_GetClassProto(*context, ids.instance) };
+    let has_prototype = unsafe { JS_GetTag__(existing) } != JS_TAG_NULL;
+    unsafe { JS_FreeValue__(*context, existing) };
+
+    if has_prototype {
+        return Ok(());
     }
-    let mut boxed = unsafe { Box::from_raw(storage_ptr) };
-    boxed.remove(&TypeId::of::<T>());
-    if boxed.len() == 0 {
-        // If our storage is now empty let's just clear opaque storage entirely. This
-        // means we don't have to worry about specifically doing so when dropping a runtime.
-        unsafe { JS_SetRuntimeOpaque(runtime, std::ptr::null_mut()) }
-    } else {
-        unsafe { JS_SetRuntimeOpaque(runtime, Box::into_raw(boxed) as _) }
+
+    let prototype = T::create_prototype(*context, ids.prototype)?;
+    if let Err(err) = T::create_constructor(*context, ids.constructor, prototype) {
+        unsafe { JS_FreeValue__(*context, prototype) };
+        return Err(err);
     }
+
+    // Rather than have to specify the prototype each time QuickJS lets us set a class
+    // prototype, which it then automatically uses. This means we don't have to keep
+    // track of prototype objects ourselves. Which is nice.
+    unsafe { JS_SetClassProto(*context, ids.instance, prototype) };
+    Ok(())
 }
 
-// We have a separate method that specifically does *not* create new class IDs because
-// we need this functionality during the prototype finalizer. At that moment we don't
-// have a reference to a JSContext to create a prototype, but we also don't want to:
-// if a class doesn't already exist inside a finalizer we've got some really weird stuff
-// going on.
-pub(super) fn get_existing_class_id<T: JSExportClass>(
+// Called when the runtime is being freed. Instance finalizers need to look up class IDs so
+// this must only happen once the runtime (and so every object in it) is gone, hence we take
+// the storage pointer rather than the runtime.
+pub(super) unsafe fn free_class_storage(storage_ptr: *mut c_void) {
+    if storage_ptr.is_null() == false {
+        drop(Box::from_raw(storage_ptr as *mut ClassIDStorage));
+    }
+}
+
+fn get_existing_ids<T: JSExportClass>(
     runtime: QuickJSRuntimeInternal,
-) -> EsperantoResult<Option<u32>> {
+) -> EsperantoResult<Option<StoredClassIDs>> {
     let type_id = TypeId::of::<T>();
     let storage_ptr = unsafe { JS_GetRuntimeOpaque(runtime) } as *mut ClassIDStorage;
     if storage_ptr.is_null() == false {
@@ -99,11 +111,41 @@ pub(super) fn get_existing_class_id<T: JSExportClass>(
                 .ok_or(JSExportError::UnexpectedBehaviour)?
         };
 
-        return Ok(storage_reg.get(&type_id).map(|s| s.instance));
+        return Ok(storage_reg.get(&type_id).copied());
     }
     Ok(None)
 }
 
+// We have a separate method that specifically does *not* create new class IDs because
+// we need this functionality during the instance finalizer. At that moment we don't
+// have a reference to a JSContext to create a prototype, but we also don't want to:
+// if a class doesn't already exist inside a finalizer we've got some really weird stuff
+// going on.
+pub(super) fn get_existing_class_id<T: JSExportClass>(
+    runtime: QuickJSRuntimeInternal,
+) -> EsperantoResult<Option<u32>> {
+    Ok(get_existing_ids::<T>(runtime)?.map(|ids| ids.instance))
+}
+
+// Used when we want the private data of an object but don't know its exact class, e.g. when
+// getting a parent class from an instance of a subclass.
+pub(super) fn find_opaque_in_any_class(
+    value: QuickJSValue,
+    runtime: QuickJSRuntimeInternal,
+) -> *mut c_void {
+    let storage_ptr = unsafe { JS_GetRuntimeOpaque(runtime) } as *mut ClassIDStorage;
+    let storage = match unsafe { storage_ptr.as_ref() } {
+        Some(storage) => storage,
+        None => return std::ptr::null_mut(),
+    };
+
+    storage
+        .values()
+        .map(|ids| unsafe { JS_GetOpaque(value, ids.instance) })
+        .find(|ptr| ptr.is_null() == false)
+        .unwrap_or(std::ptr::null_mut())
+}
+
 // We don't need to define our custom JS classses upfront so at any point we can call
 // this method to either grab the existing class or define a new one on demand.
 pub(super) fn get_or_create_class_id<T: JSExportClass>(
@@ -111,13 +153,15 @@ pub(super) fn get_or_create_class_id<T: JSExportClass>(
 ) -> EsperantoResult<u32> {
     let runtime = context.get_runtime();
 
-    if let Some(existing) = get_existing_class_id::<T>(runtime)? {
-        return Ok(existing);
+    if let Some(existing) = get_existing_ids::<T>(runtime)? {
+        ensure_prototype_exists::<T>(context, &existing)?;
+        return Ok(existing.instance);
     }
 
     let type_id = TypeId::of::<T>();
 
-    let ids = create_ids::<T>(context, runtime)?;
+    let ids = create_ids::<T>(runtime)?;
+    ensure_prototype_exists::<T>(context, &ids)?;
     let instance_id = ids.instance;
     let storage_ptr: *mut ClassIDStorage = unsafe { JS_GetRuntimeOpaque(runtime) as _ };
 
@@ -1,16 +1,18 @@
-use std::ffi::CString;
+use std::ffi::{c_void, CString};
+use std::os::raw::c_char;
 
 use quickjs_android_suitable_sys::{
-    JS_Eval, JS_FreeContext, JS_GetGlobalObject, JS_GetRuntime, JS_NewContext, JS_RunGC, JS_Throw,
+    JSRuntime as QuickJSRuntime, JS_Eval, JS_FreeContext, JS_GetContextOpaque, JS_GetGlobalObject,
+    JS_GetRuntime, JS_NewContext, JS_ParseJSON, JS_RunGC, JS_SetContextOpaque, JS_Throw,
     JS_EVAL_TYPE_GLOBAL,
 };
 
 use super::quickjscontextpointer::QuickJSContextPointer;
 use super::quickjsruntime::QuickJSRuntimeInternal;
 use crate::shared::{
-    context::{EvaluateMetadata, JSContextError, JSContextInternal},
+    context::{EvaluateMetadata, JSContextError, JSContextImplementation},
     errors::EsperantoResult,
-    value::JSValueInternal,
+    value::JSValueImplementation,
 };
 
 use super::quickjsvalue::QuickJSValueInternal;
@@ -28,14 +30,20 @@ impl QuickJSContextInternal {
         let retained = err.retain(self);
         unsafe { JS_Throw(*self, retained) };
     }
+
+    // Unlike JavaScriptCore we don't need to keep a reference to the runtime around
+    // because QuickJS will always give it to us
+    pub(crate) fn get_runtime(self) -> *mut QuickJSRuntime {
+        unsafe { JS_GetRuntime(*self) }
+    }
 }
 
-impl JSContextInternal for QuickJSContextInternal {
+impl JSContextImplementation for QuickJSContextInternal {
     type RuntimeType = QuickJSRuntimeInternal;
     type ValueType = QuickJSValueInternal;
 
-    fn new_in_runtime(runtime: Self::RuntimeType) -> Result<Self, JSContextError> {
-        let raw = unsafe { JS_NewContext(runtime) };
+    fn new_in_runtime(runtime: &Self::RuntimeType) -> Result<Self, JSContextError> {
+        let raw = unsafe { JS_NewContext(*runtime) };
         match raw.is_null() {
             true => Err(JSContextError::CouldNotCreateContext),
             false => Ok(QuickJSContextPointer::wrap(raw, true)),
@@ -51,7 +59,7 @@ impl JSContextInternal for QuickJSContextInternal {
         // let line_number = metadata.map(|m| m.line_number).unwrap_or(0);
         let filename = metadata
             .map(|m| m.filename.as_ptr())
-            .unwrap_or(PLACEHOLDER_FILENAME.as_ptr() as *const i8);
+            .unwrap_or(PLACEHOLDER_FILENAME.as_ptr() as *const c_char);
 
         check_quickjs_exception!(self => {
             unsafe {
@@ -66,16 +74,31 @@ impl JSContextInternal for QuickJSContextInternal {
         })
     }
 
+    fn parse_json(self, json: &str) -> EsperantoResult<Self::ValueType> {
+        // JS_ParseJSON stops at json_size but still expects a NUL after it. We can't use a
+        // CString because the JSON itself might contain NUL, which should be a SyntaxError.
+        let mut buffer = Vec::with_capacity(json.len() + 1);
+        buffer.extend_from_slice(json.as_bytes());
+        buffer.push(0);
+
+        check_quickjs_exception!(self => {
+            unsafe {
+                JS_ParseJSON(
+                    *self,
+                    buffer.as_ptr() as *const c_char,
+                    json.len(),
+                    PLACEHOLDER_FILENAME.as_ptr() as *const c_char,
+                )
+            }
+        })
+    }
+
     fn release(self) {
         if self.free_on_drop {
             unsafe { JS_FreeContext(*self) }
         }
     }
 
-    fn get_runtime(self) -> Self::RuntimeType {
-        unsafe { JS_GetRuntime(*self) }
-    }
-
     fn garbage_collect(self) {
         unsafe { JS_RunGC(self.get_runtime()) }
     }
@@ -84,4 +107,13 @@ impl JSContextInternal for QuickJSContextInternal {
         let obj = unsafe { JS_GetGlobalObject(*self) };
         obj.into()
     }
+
+    fn get_private_data(self) -> EsperantoResult<*mut c_void> {
+        Ok(unsafe { JS_GetContextOpaque(*self) })
+    }
+
+    fn set_private_data(self, data: *mut c_void) -> EsperantoResult<()> {
+        unsafe { JS_SetContextOpaque(*self, data) };
+        Ok(())
+    }
 }
@@ -23,6 +23,8 @@ impl<'c> PartialEq for QuickJSContextPointer {
     }
 }
 
+impl Eq for QuickJSContextPointer {}
+
 impl<'c> Deref for QuickJSContextPointer {
     type Target = *mut JSContext;
 
@@ -1,33 +1,39 @@
-use std::{convert::TryInto, ffi::CString, slice};
+use std::{convert::TryInto, ffi::CString, os::raw::c_char};
 
 use quickjs_android_suitable_sys::{
     JSClassCall, JSClassDef, JSContext as QuickJSContext, JSRuntime as QuickJSRuntime,
-    JSValue as QuickJSValue, JS_GetClassProto, JS_GetOpaque, JS_GetRuntime, JS_NewClass,
-    JS_NewObjectClass, JS_SetClassProto, JS_SetConstructorBit, JS_CALL_FLAG_CONSTRUCTOR,
+    JSValue as QuickJSValue, JS_DefinePropertyGetSet, JS_DefinePropertyValueStr, JS_FreeAtom,
+    JS_FreeValue__, JS_GetClassProto, JS_GetOpaque, JS_GetPropertyStr, JS_IsEqual__, JS_IsObject__,
+    JS_NewAtom, JS_NewCFunctionData, JS_NewClass, JS_NewObjectClass, JS_NewString,
+    JS_SetClassProto, JS_SetConstructor, JS_SetConstructorBit, JS_SetPrototype,
+    JS_CALL_FLAG_CONSTRUCTOR, JS_EXCEPTION__, JS_PROP_CONFIGURABLE, JS_PROP_WRITABLE,
+    JS_UNDEFINED__,
 };
 
 use crate::{
-    export::{JSClassFunction, JSExportPrivateData},
+    export::{
+        attribute_at_index, attributes_for, JSClassFunction, JSExportAttribute, JSExportPrivateData,
+    },
     quickjs::{
         quickjs_class_storage::get_existing_class_id, quickjscontextpointer::QuickJSContextPointer,
     },
     shared::{
         errors::{EsperantoResult, JSExportError, JavaScriptError},
-        value::JSValueInternal,
+        util::arguments_from_raw_parts,
+        value::{JSValueImplementation, NativeFunction},
     },
-    EsperantoError, JSContext, JSExportClass, JSRuntime, JSValue, Retain,
+    EsperantoError, JSContext, JSExportClass, JSValue, Retain,
 };
 
-use super::quickjs_class_storage::clear_class;
-
-pub(crate) type QuickJSClassID = u32;
+static NAME_PROPERTY: &[u8] = b"name\0";
+static CONSTRUCTOR_PROPERTY: &[u8] = b"constructor\0";
+static PROTOTYPE_PROPERTY: &[u8] = b"prototype\0";
 
 pub(super) trait QuickJSExportExtensions: JSExportClass + Sized {
     fn create_prototype_class(
-        context: *mut QuickJSContext,
+        runtime: *mut QuickJSRuntime,
         prototype_class_id: u32,
     ) -> EsperantoResult<()> {
-        let runtime = unsafe { JS_GetRuntime(context) };
         let name_cstring = CString::new(Self::CLASS_NAME)?;
 
         let call: JSClassCall;
@@ -40,7 +46,7 @@ pub(super) trait QuickJSExportExtensions: JSExportClass + Sized {
         let definition = JSClassDef {
             class_name: name_cstring.as_ptr(),
             call,
-            finalizer: Some(finalize_prototype::<Self>),
+            finalizer: None,
             gc_mark: None,
             exotic: std::ptr::null_mut(),
         };
@@ -49,18 +55,6 @@ pub(super) trait QuickJSExportExtensions: JSExportClass + Sized {
             return Err(JSExportError::UnexpectedBehaviour.into());
         }
 
-        // we (seemingly?) need to make sure our prototype has its own prototype set to Object.
-
-        let object = {
-            // Grabbed the const value for JS_CLASS_OBJECT from here:
-            // https://github.com/bellard/quickjs/blob/2788d71e823b522b178db3b3660ce93689534e6d/quickjs.c#L120
-
-            const JS_CLASS_OBJECT: u32 = 1;
-            unsafe { JS_GetClassProto(context, JS_CLASS_OBJECT) }
-        };
-
-        unsafe { JS_SetClassProto(context, prototype_class_id, object) };
-
         Ok(())
     }
 
@@ -83,7 +77,27 @@ pub(super) trait QuickJSExportExtensions: JSExportClass + Sized {
         Ok(())
     }
 
-    fn create_prototype(context: *mut QuickJSContext, prototype_class_id: u32) -> QuickJSValue {
+    fn create_prototype(
+        context: *mut QuickJSContext,
+        prototype_class_id: u32,
+    ) -> EsperantoResult<QuickJSValue> {
+        // we (seemingly?) need to make sure our prototype has its own prototype set to Object.
+        // Class prototypes are per-context so this has to happen for every new context.
+
+        // Subclasses use their parent's prototype instead.
+        let object = match parent_prototype::<Self>(context)? {
+            Some(parent) => parent,
+            None => {
+                // Grabbed the const value for JS_CLASS_OBJECT from here:
+                // https://github.com/bellard/quickjs/blob/2788d71e823b522b178db3b3660ce93689534e6d/quickjs.c#L120
+
+                const JS_CLASS_OBJECT: u32 = 1;
+                unsafe { JS_GetClassProto(context, JS_CLASS_OBJECT) }
+            }
+        };
+
+        unsafe { JS_SetClassProto(context, prototype_class_id, object) };
+
         // weird quirk in the QuickJS API: created class IDs are u32, JS_NewObjectClass requires
         // i32. Assume it's just an oversight in the header.
 
@@ -93,12 +107,235 @@ pub(super) trait QuickJSExportExtensions: JSExportClass + Sized {
             unsafe { JS_SetConstructorBit(context, prototype, 1) };
         }
 
-        prototype
+        if let Err(err) = Self::install_attributes::<false>(context, prototype) {
+            unsafe { JS_FreeValue__(context, prototype) };
+            return Err(err);
+        }
+
+        Ok(prototype)
+    }
+
+    fn create_constructor_class(
+        runtime: *mut QuickJSRuntime,
+        constructor_class_id: u32,
+    ) -> EsperantoResult<()> {
+        let name_cstring = CString::new(Self::CLASS_NAME)?;
+
+        // Unlike the prototype the constructor is always callable, even if only to throw an
+        // error. Otherwise things like `instanceof` refuse to work with it.
+        let definition = JSClassDef {
+            class_name: name_cstring.as_ptr(),
+            call: Some(class_prototype_call::<Self>),
+            finalizer: None,
+            gc_mark: None,
+            exotic: std::ptr::null_mut(),
+        };
+
+        if unsafe { JS_NewClass(runtime, constructor_class_id, &definition) } != 0 {
+            return Err(JSExportError::UnexpectedBehaviour.into());
+        }
+
+        Ok(())
+    }
+
+    /// Creates the constructor object for a class and links it to the prototype via the
+    /// `prototype` and `constructor` properties. The prototype then keeps the constructor alive.
+    fn create_constructor(
+        context: *mut QuickJSContext,
+        constructor_class_id: u32,
+        prototype: QuickJSValue,
+    ) -> EsperantoResult<()> {
+        // Like a JS class that extends another, a subclass constructor inherits from the parent
+        // constructor (and so gets its static attributes too).
+        let function_prototype = match parent_prototype::<Self>(context)? {
+            Some(parent) => {
+                let parent_constructor = unsafe {
+                    JS_GetPropertyStr(
+                        context,
+                        parent,
+                        CONSTRUCTOR_PROPERTY.as_ptr() as *const c_char,
+                    )
+                };
+                unsafe { JS_FreeValue__(context, parent) };
+                parent_constructor
+            }
+            None => {
+                // Same as JS_CLASS_OBJECT above. JS_CLASS_BYTECODE_FUNCTION's class prototype is
+                // Function.prototype.
+                const JS_CLASS_BYTECODE_FUNCTION: u32 = 13;
+                unsafe { JS_GetClassProto(context, JS_CLASS_BYTECODE_FUNCTION) }
+            }
+        };
+        unsafe { JS_SetClassProto(context, constructor_class_id, function_prototype) };
+
+        let constructor = unsafe { JS_NewObjectClass(context, constructor_class_id as _) };
+
+        if Self::CALL_AS_CONSTRUCTOR.is_some() {
+            unsafe { JS_SetConstructorBit(context, constructor, 1) };
+        }
+
+        let name_cstring = CString::new(Self::CLASS_NAME)?;
+        let name_value = unsafe { JS_NewString(context, name_cstring.as_ptr()) };
+        unsafe {
+            JS_DefinePropertyValueStr(
+                context,
+                constructor,
+                NAME_PROPERTY.as_ptr() as *const c_char,
+                name_value,
+                JS_PROP_CONFIGURABLE as i32,
+            )
+        };
+
+        let install_result = Self::install_attributes::<true>(context, constructor);
+        if install_result.is_ok() {
+            // JS_SetConstructor retains both values itself
+            unsafe { JS_SetConstructor(context, constructor, prototype) };
+        }
+        unsafe { JS_FreeValue__(context, constructor) };
+        install_result
+    }
+
+    // Adds either ATTRIBUTES or STATIC_ATTRIBUTES to the target object
+    fn install_attributes<const STATIC: bool>(
+        context: *mut QuickJSContext,
+        target: QuickJSValue,
+    ) -> EsperantoResult<()> {
+        let attributes = match attributes_for::<Self>(STATIC) {
+            Some(attributes) => attributes,
+            None => return Ok(()),
+        };
+
+        for (index, (name, attribute)) in attributes.entries().enumerate() {
+            let name_cstring = CString::new(*name)?;
+            match attribute {
+                JSExportAttribute::Function(method) => {
+                    // QuickJS gives us a "magic" integer we can attach to a C function. We use
+                    // it to store the index of the method so we can look it back up when called.
+                    let function = unsafe {
+                        JS_NewCFunctionData(
+                            context,
+                            Some(class_method_call::<Self, STATIC>),
+                            method.num_args,
+                            index as i32,
+                            0,
+                            std::ptr::null_mut(),
+                        )
+                    };
+
+                    // JS_NewCFunctionData doesn't let us specify a name so we set it manually.
+                    // Both JS_DefinePropertyValueStr calls take ownership of the values passed in.
+                    let name_value = unsafe { JS_NewString(context, name_cstring.as_ptr()) };
+                    unsafe {
+                        JS_DefinePropertyValueStr(
+                            context,
+                            function,
+                            NAME_PROPERTY.as_ptr() as *const c_char,
+                            name_value,
+                            JS_PROP_CONFIGURABLE as i32,
+                        )
+                    };
+
+                    // Same flags as a method defined in a JS class: not enumerable.
+                    let result = unsafe {
+                        JS_DefinePropertyValueStr(
+                            context,
+                            target,
+                            name_cstring.as_ptr(),
+                            function,
+                            (JS_PROP_WRITABLE | JS_PROP_CONFIGURABLE) as i32,
+                        )
+                    };
+                    if result < 0 {
+                        return Err(JSExportError::UnexpectedBehaviour.into());
+                    }
+                }
+                JSExportAttribute::Property { setter, .. } => {
+                    let getter_function = unsafe {
+                        JS_NewCFunctionData(
+                            context,
+                            Some(class_getter_call::<Self, STATIC>),
+                            0,
+                            index as i32,
+                            0,
+                            std::ptr::null_mut(),
+                        )
+                    };
+
+                    // Without a setter QuickJS treats the property as read-only, throwing
+                    // a TypeError when it's assigned to in strict mode.
+                    let setter_function = match setter {
+                        Some(_) => unsafe {
+                            JS_NewCFunctionData(
+                                context,
+                                Some(class_setter_call::<Self, STATIC>),
+                                1,
+                                index as i32,
+                                0,
+                                std::ptr::null_mut(),
+                            )
+                        },
+                        None => unsafe { JS_UNDEFINED__ },
+                    };
+
+                    // JS_DefinePropertyGetSet frees both the getter and setter
+                    let atom = unsafe { JS_NewAtom(context, name_cstring.as_ptr()) };
+                    let result = unsafe {
+                        JS_DefinePropertyGetSet(
+                            context,
+                            target,
+                            atom,
+                            getter_function,
+                            setter_function,
+                            JS_PROP_CONFIGURABLE as i32,
+                        )
+                    };
+                    unsafe { JS_FreeAtom(context, atom) };
+                    if result < 0 {
+                        return Err(JSExportError::UnexpectedBehaviour.into());
+                    }
+                }
+                JSExportAttribute::Constant(create_value) => {
+                    let context_ptr = QuickJSContextPointer::wrap(context, false);
+                    let value = create_value(JSContext::borrow_from_implementation(context_ptr)?)?;
+
+                    // No flags means read-only, not enumerable and not configurable. The call
+                    // takes ownership of the value so it gets a reference of its own.
+                    let result = unsafe {
+                        JS_DefinePropertyValueStr(
+                            context,
+                            target,
+                            name_cstring.as_ptr(),
+                            value.internal.retain(context_ptr),
+                            0,
+                        )
+                    };
+                    if result < 0 {
+                        return Err(JSExportError::UnexpectedBehaviour.into());
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 }
 
 impl<T> QuickJSExportExtensions for T where T: JSExportClass {}
 
+// Gets the (retained) prototype of the class's parent, creating it in this context if need be.
+fn parent_prototype<T: JSExportClass>(
+    context: *mut QuickJSContext,
+) -> EsperantoResult<Option<QuickJSValue>> {
+    let parent = match T::PARENT {
+        Some(parent) => parent,
+        None => return Ok(None),
+    };
+
+    let context_ptr = QuickJSContextPointer::wrap(context, false);
+    let wrapped_context = JSContext::borrow_from_implementation(context_ptr)?;
+    let prototype = (parent.prototype)(wrapped_context)?;
+    Ok(Some(prototype.internal.retain(context_ptr)))
+}
+
 unsafe extern "C" fn class_prototype_call<T: JSExportClass>(
     ctx: *mut QuickJSContext,
     func_obj: QuickJSValue,
@@ -109,16 +346,16 @@ unsafe extern "C" fn class_prototype_call<T: JSExportClass>(
 ) -> QuickJSValue {
     let called_as_constructor =
         flags & JS_CALL_FLAG_CONSTRUCTOR as i32 == JS_CALL_FLAG_CONSTRUCTOR as i32;
-    let runtime_ptr = JS_GetRuntime(ctx);
     let context_ptr = QuickJSContextPointer::wrap(ctx, false);
-    let runtime = JSRuntime::from_raw(runtime_ptr, false);
-    let context = JSContext::from_raw_storing_runtime(context_ptr, runtime);
+    let context = JSContext::borrow_from_implementation(context_ptr).unwrap();
 
-    let args: Vec<JSValue> = slice::from_raw_parts(argv, argc.try_into().unwrap())
+    let args: Vec<JSValue> = arguments_from_raw_parts(argv, argc.try_into().unwrap())
         .iter()
         .map(|raw| JSValue::wrap_internal(*raw, &context))
         .collect();
 
+    let arg_refs: Vec<&JSValue> = args.iter().collect();
+
     let execution_target: Option<JSClassFunction>;
     let error: EsperantoError;
     if called_as_constructor {
@@ -139,28 +376,180 @@ unsafe extern "C" fn class_prototype_call<T: JSExportClass>(
         .into();
     };
 
-    let execution_result: EsperantoResult<Retain<JSValue>>;
+    let mut execution_result: EsperantoResult<Retain<JSValue>>;
 
     if let Some(to_execute) = execution_target {
-        execution_result = (to_execute.func)(&args, &context);
+        execution_result = (to_execute.func)(&arg_refs, &context);
     } else {
         execution_result = Err(error.into())
     }
-    //Class constructor Test cannot be invoked without 'new'
-    let val = execution_result.unwrap_or_else(|err| {
-        let error_jsval = JSValue::try_new_from(err, &context).unwrap();
-        context_ptr.throw_error(error_jsval.internal);
-        JSValue::undefined(&context)
-    });
-
-    val.internal.retain(context_ptr)
+
+    if called_as_constructor && JS_IsEqual__(func_obj, new_target) == 0 {
+        execution_result = execution_result.and_then(|instance| {
+            use_new_target_prototype(context_ptr, instance.internal, new_target)?;
+            Ok(instance)
+        });
+    }
+
+    return_or_throw(execution_result, context)
 }
 
-pub(super) unsafe extern "C" fn finalize_prototype<T: JSExportClass>(
-    runtime: *mut QuickJSRuntime,
-    _: QuickJSValue,
-) {
-    clear_class::<T>(runtime);
+// When a JS class extends one of ours its constructor calls ours via super(), passing itself
+// as new.target. The instance we create should then use the subclass prototype rather than
+// the one we'd normally give it.
+unsafe fn use_new_target_prototype(
+    ctx: QuickJSContextPointer,
+    instance: QuickJSValue,
+    new_target: QuickJSValue,
+) -> EsperantoResult<()> {
+    // new.target can be a proxy (or have a getter) that throws when we read the prototype
+    let prototype = check_quickjs_exception!(ctx => {
+        JS_GetPropertyStr(*ctx, new_target, PROTOTYPE_PROPERTY.as_ptr() as *const c_char)
+    })?;
+    let result = if JS_IsObject__(instance) == 1 && JS_IsObject__(prototype) == 1 {
+        // Throws rather than returning false if the instance can't take the new prototype
+        check_quickjs_exception!(ctx => JS_SetPrototype(*ctx, instance, prototype)).map(|_| ())
+    } else {
+        Ok(())
+    };
+    JS_FreeValue__(*ctx, prototype);
+    result
+}
+
+// Methods, getters and setters are all created with JS_NewCFunctionData, using the "magic"
+// value to store the index of the attribute they were created from. STATIC tells us whether
+// that's an index into ATTRIBUTES or STATIC_ATTRIBUTES.
+unsafe extern "C" fn class_method_call<T: JSExportClass, const STATIC: bool>(
+    ctx: *mut QuickJSContext,
+    this_val: QuickJSValue,
+    argc: i32,
+    argv: *mut QuickJSValue,
+    magic: i32,
+    _func_data: *mut QuickJSValue,
+) -> QuickJSValue {
+    let context_ptr = QuickJSContextPointer::wrap(ctx, false);
+    let context = JSContext::borrow_from_implementation(context_ptr).unwrap();
+
+    let this_obj = JSValue::wrap_internal(this_val, context);
+    let args: Vec<JSValue> = arguments_from_raw_parts(argv, argc.try_into().unwrap())
+        .iter()
+        .map(|raw| JSValue::wrap_internal(*raw, context))
+        .collect();
+
+    let arg_refs: Vec<&JSValue> = args.iter().collect();
+
+    let attributes = attributes_for::<T>(STATIC);
+    let execution_result = match attribute_at_index(&attributes, magic as usize) {
+        Some(JSExportAttribute::Function(method)) => (method.func)(context, &this_obj, &arg_refs),
+        // Should never happen: we only ever create methods from the attributes list.
+        _ => Err(JSExportError::UnexpectedBehaviour.into()),
+    };
+
+    return_or_throw(execution_result, context)
+}
+
+unsafe extern "C" fn class_getter_call<T: JSExportClass, const STATIC: bool>(
+    ctx: *mut QuickJSContext,
+    this_val: QuickJSValue,
+    _argc: i32,
+    _argv: *mut QuickJSValue,
+    magic: i32,
+    _func_data: *mut QuickJSValue,
+) -> QuickJSValue {
+    let context_ptr = QuickJSContextPointer::wrap(ctx, false);
+    let context = JSContext::borrow_from_implementation(context_ptr).unwrap();
+    let this_obj = JSValue::wrap_internal(this_val, context);
+
+    let attributes = attributes_for::<T>(STATIC);
+    let execution_result = match attribute_at_index(&attributes, magic as usize) {
+        Some(JSExportAttribute::Property { getter, .. }) => getter(context, &this_obj),
+        _ => Err(JSExportError::UnexpectedBehaviour.into()),
+    };
+
+    return_or_throw(execution_result, context)
+}
+
+unsafe extern "C" fn class_setter_call<T: JSExportClass, const STATIC: bool>(
+    ctx: *mut QuickJSContext,
+    this_val: QuickJSValue,
+    argc: i32,
+    argv: *mut QuickJSValue,
+    magic: i32,
+    _func_data: *mut QuickJSValue,
+) -> QuickJSValue {
+    let context_ptr = QuickJSContextPointer::wrap(ctx, false);
+    let context = JSContext::borrow_from_implementation(context_ptr).unwrap();
+    let this_obj = JSValue::wrap_internal(this_val, context);
+
+    // QuickJS always calls setters with one argument, but if someone grabs the setter via
+    // Object.getOwnPropertyDescriptor they could call it with none.
+    let new_value = match argc {
+        0 => JSValue::wrap_internal(JS_UNDEFINED__, context),
+        _ => JSValue::wrap_internal(*argv, context),
+    };
+
+    let attributes = attributes_for::<T>(STATIC);
+    let execution_result = match attribute_at_index(&attributes, magic as usize) {
+        Some(JSExportAttribute::Property {
+            setter: Some(setter),
+            ..
+        }) => setter(context, &this_obj, &new_value).map(|_| JSValue::undefined(context)),
+        _ => Err(JSExportError::UnexpectedBehaviour.into()),
+    };
+
+    return_or_throw(execution_result, context)
+}
+
+// QuickJS doesn't let us attach private data to a C function but it does let us attach JS values.
+// So we wrap native function closures in an instance of this class, which also means they're
+// dropped by the usual instance finalizer when the function is garbage collected.
+pub(super) struct NativeFunctionHolder(pub(super) Box<NativeFunction>);
+
+impl JSExportClass for NativeFunctionHolder {
+    const CLASS_NAME: &'static str = "NativeFunction";
+}
+
+pub(super) unsafe extern "C" fn native_function_call(
+    ctx: *mut QuickJSContext,
+    this_val: QuickJSValue,
+    argc: i32,
+    argv: *mut QuickJSValue,
+    _magic: i32,
+    func_data: *mut QuickJSValue,
+) -> QuickJSValue {
+    let context_ptr = QuickJSContextPointer::wrap(ctx, false);
+    let context = JSContext::borrow_from_implementation(context_ptr).unwrap();
+
+    let this_obj = JSValue::wrap_internal(this_val, context);
+    let args: Vec<JSValue> = arguments_from_raw_parts(argv, argc.try_into().unwrap())
+        .iter()
+        .map(|raw| JSValue::wrap_internal(*raw, context))
+        .collect();
+
+    let arg_refs: Vec<&JSValue> = args.iter().collect();
+
+    let holder = JSValue::wrap_internal(*func_data, context);
+    let execution_result = holder
+        .as_native::<NativeFunctionHolder>()
+        .and_then(|native| (native.0)(context, &this_obj, &arg_refs));
+
+    return_or_throw(execution_result, context)
+}
+
+fn return_or_throw<'r, 'c>(
+    result: EsperantoResult<Retain<JSValue<'r, 'c>>>,
+    context: &'c JSContext<'r, 'c>,
+) -> QuickJSValue {
+    match result {
+        Ok(val) => val.internal.retain(context.implementation()),
+        Err(err) => {
+            let error_jsval = JSValue::try_new_from(err, context).unwrap();
+            context.implementation().throw_error(error_jsval.internal);
+            // QuickJS checks the return value rather than the exception state to work out
+            // whether a call failed, so we need to return the special exception value:
+            unsafe { JS_EXCEPTION__ }
+        }
+    }
 }
 
 pub(super) unsafe extern "C" fn finalize_instance<T: JSExportClass>(
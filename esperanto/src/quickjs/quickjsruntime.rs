@@ -1,10 +1,13 @@
-use quickjs_android_suitable_sys::{JSRuntime as QuickJSRuntime, JS_FreeRuntime, JS_NewRuntime};
+use quickjs_android_suitable_sys::{
+    JSRuntime as QuickJSRuntime, JS_FreeRuntime, JS_GetRuntimeOpaque, JS_NewRuntime,
+};
 
-use crate::shared::runtime::{JSRuntimeError, JSRuntimeInternal};
+use super::quickjs_class_storage::free_class_storage;
+use crate::shared::runtime::{JSRuntimeError, JSRuntimeImplementation};
 
-pub type QuickJSRuntimeInternal = *mut QuickJSRuntime;
+pub(crate) type QuickJSRuntimeInternal = *mut QuickJSRuntime;
 
-impl JSRuntimeInternal for QuickJSRuntimeInternal {
+impl JSRuntimeImplementation for QuickJSRuntimeInternal {
     fn new() -> Result<Self, JSRuntimeError> {
         let runtime = unsafe { JS_NewRuntime() };
         if runtime.is_null() {
@@ -13,7 +16,11 @@ impl JSRuntimeInternal for QuickJSRuntimeInternal {
         Ok(runtime)
     }
 
-    fn release(self) {
-        unsafe { JS_FreeRuntime(self) }
+    fn release(&mut self) {
+        // Freeing the runtime finalizes any remaining instances of our exported classes, which
+        // needs the class storage. So we grab it first and only free it afterwards.
+        let class_storage = unsafe { JS_GetRuntimeOpaque(*self) };
+        unsafe { JS_FreeRuntime(*self) };
+        unsafe { free_class_storage(class_storage) };
     }
 }
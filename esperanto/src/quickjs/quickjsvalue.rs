@@ -1,36 +1,48 @@
 use std::ffi::{c_void, CStr, CString};
+use std::os::raw::c_char;
 
 use quickjs_android_suitable_sys::{
-    JSValue as QuickJSValue, JS_Call, JS_CallConstructor, JS_DeleteProperty, JS_DupValue__,
-    JS_FreeAtom, JS_FreeCString, JS_FreeValue__, JS_GetClassProto, JS_GetOpaque, JS_GetPropertyStr,
-    JS_GetRuntime, JS_IsConstructor, JS_IsEqual__, JS_IsError, JS_IsInstanceOf, JS_IsObject__,
-    JS_IsString__, JS_NewAtom, JS_NewBool__, JS_NewError, JS_NewFloat64__, JS_NewObject,
-    JS_NewObjectClass, JS_NewObjectProtoClass, JS_NewString, JS_SetOpaque, JS_SetPropertyStr,
-    JS_ToBool, JS_ToCStringLen2, JS_ToFloat64, JS_UNDEFINED__,
+    js_free, JSAtom, JSPropertyEnum, JSValue as QuickJSValue, JS_AtomToValue, JS_Call,
+    JS_CallConstructor, JS_DeleteProperty, JS_DupValue__, JS_FreeAtom, JS_FreeCString,
+    JS_FreeValue__, JS_GetClassProto, JS_GetOpaque, JS_GetOwnPropertyNames, JS_GetPropertyInternal,
+    JS_GetPropertyStr, JS_GetPropertyUint32, JS_GetPrototype, JS_GetTag__, JS_IsArray,
+    JS_IsConstructor, JS_IsEqual__, JS_IsError, JS_IsFunction, JS_IsInstanceOf, JS_IsObject__,
+    JS_IsString__, JS_IsUndefined__, JS_JSONStringify, JS_NewArray, JS_NewAtomUInt32,
+    JS_NewBigInt64, JS_NewBigUint64, JS_NewBool__, JS_NewCFunctionData, JS_NewError,
+    JS_NewFloat64__, JS_NewObject, JS_NewObjectClass, JS_NewString, JS_NewStringLen, JS_SetOpaque,
+    JS_SetPropertyInternal, JS_SetPropertyStr, JS_SetPropertyUint32, JS_ToBool, JS_ToCStringLen2,
+    JS_ToFloat64, JS_ValueToAtom, JS_GPN_ENUM_ONLY, JS_GPN_STRING_MASK, JS_NULL__, JS_PROP_THROW,
+    JS_TAG_BIG_INT, JS_TAG_BOOL, JS_TAG_FLOAT64, JS_TAG_INT, JS_TAG_NULL, JS_TAG_SYMBOL,
+    JS_UNDEFINED__,
 };
 
 use crate::{
-    export::{get_stored_prototype_info, JSExportPrivateData},
+    export::JSExportPrivateData,
     shared::{
-        context::JSContextInternal,
+        context::{get_intrinsic, Intrinsic, JSContextImplementation},
         errors::CatchExceptionError,
         errors::EsperantoResult,
         errors::{EsperantoError, JSExportError},
-        value::{JSValueError, JSValueInternal},
+        util::{utf16_from_wtf8, wtf8_from_utf16},
+        value::{ErrorLocation, JSValueError, JSValueImplementation, NativeFunction, PropertyKey},
     },
     JSExportClass, JSValue,
 };
 
-use super::quickjscontextpointer::QuickJSContextPointer;
-use super::{
-    quickjs_class_storage::get_or_create_class_id, quickjsexport::QuickJSExportExtensions,
+use super::quickjs_class_storage::{
+    find_opaque_in_any_class, get_existing_class_id, get_or_create_class_id,
 };
+use super::quickjscontextpointer::QuickJSContextPointer;
+use super::quickjsexport::{native_function_call, NativeFunctionHolder};
 
-pub type QuickJSValueInternal = QuickJSValue;
+pub(crate) type QuickJSValueInternal = QuickJSValue;
 
-static CONSTRUCTOR_STRING: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"constructor\0") };
+static PROTOTYPE_STRING: &[u8] = b"prototype\0";
+static FILE_NAME_STRING: &[u8] = b"fileName\0";
+static LINE_NUMBER_STRING: &[u8] = b"lineNumber\0";
+static STACK_STRING: &[u8] = b"stack\0";
 
-impl JSValueInternal for QuickJSValueInternal {
+impl JSValueImplementation for QuickJSValueInternal {
     type ContextType = QuickJSContextPointer;
 
     fn retain(self, ctx: Self::ContextType) -> Self {
@@ -42,6 +54,8 @@ impl JSValueInternal for QuickJSValueInternal {
     }
 
     fn as_cstring(self, ctx: Self::ContextType) -> EsperantoResult<std::ffi::CString> {
+        // The pointer is null if the conversion threw (e.g. a Symbol, or a toString() that
+        // throws), in which case the macro returns the exception instead
         let ptr = check_quickjs_exception!(ctx => {
             unsafe { JS_ToCStringLen2(*ctx, std::ptr::null_mut(), self, 0) }
         })?;
@@ -52,10 +66,59 @@ impl JSValueInternal for QuickJSValueInternal {
         Ok(cstring)
     }
 
+    fn to_json_string(
+        self,
+        indent: u32,
+        ctx: Self::ContextType,
+    ) -> EsperantoResult<Option<CString>> {
+        let space = Self::from_number(indent as f64, ctx)?;
+        let json = check_quickjs_exception!(ctx => {
+            unsafe { JS_JSONStringify(*ctx, self, JS_UNDEFINED__, space) }
+        })?;
+
+        // Same as JSON.stringify(), we get undefined back if the value can't be represented
+        if json.is_undefined(ctx) {
+            return Ok(None);
+        }
+
+        let result = json.as_cstring(ctx);
+        json.release(ctx);
+        result.map(Some)
+    }
+
     fn from_cstring(value: &std::ffi::CString, ctx: Self::ContextType) -> Self {
         unsafe { JS_NewString(*ctx, value.as_ptr()) }.into()
     }
 
+    fn as_wtf8(self, ctx: Self::ContextType) -> EsperantoResult<Vec<u8>> {
+        let mut len = 0;
+        // Null if the conversion threw, same as in as_cstring
+        let ptr = check_quickjs_exception!(ctx => {
+            unsafe { JS_ToCStringLen2(*ctx, &mut len, self, 0) }
+        })?;
+
+        // QuickJS encodes unpaired surrogates as three byte sequences, i.e. this is WTF-8
+        let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) }.to_vec();
+        unsafe { JS_FreeCString(*ctx, ptr) };
+        Ok(bytes)
+    }
+
+    fn from_wtf8(value: &[u8], ctx: Self::ContextType) -> EsperantoResult<Self> {
+        check_quickjs_exception!(ctx => {
+            unsafe { JS_NewStringLen(*ctx, value.as_ptr() as *const c_char, value.len()) }
+        })
+    }
+
+    // QuickJS has no UTF-16 API so we go via WTF-8, which it reads and writes losslessly
+
+    fn as_utf16(self, ctx: Self::ContextType) -> EsperantoResult<Vec<u16>> {
+        Ok(utf16_from_wtf8(&self.as_wtf8(ctx)?))
+    }
+
+    fn from_utf16(value: &[u16], ctx: Self::ContextType) -> EsperantoResult<Self> {
+        Self::from_wtf8(&wtf8_from_utf16(value), ctx)
+    }
+
     fn as_number(self, ctx: Self::ContextType) -> EsperantoResult<f64> {
         let mut result = 0.0;
         let success = unsafe { JS_ToFloat64(*ctx, &mut result, self) };
@@ -89,6 +152,22 @@ impl JSValueInternal for QuickJSValueInternal {
         .into())
     }
 
+    fn is_bigint(self, _: Self::ContextType) -> bool {
+        unsafe { JS_GetTag__(self) == JS_TAG_BIG_INT }
+    }
+
+    fn from_i64_bigint(value: i64, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        check_quickjs_exception!(ctx => {
+            unsafe { JS_NewBigInt64(*ctx, value) }
+        })
+    }
+
+    fn from_u64_bigint(value: u64, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        check_quickjs_exception!(ctx => {
+            unsafe { JS_NewBigUint64(*ctx, value) }
+        })
+    }
+
     fn new_error(name: CString, message: CString, ctx: Self::ContextType) -> Self {
         let err = unsafe { JS_NewError(*ctx) };
         let name_jsv = Self::from_cstring(&name, ctx);
@@ -97,14 +176,15 @@ impl JSValueInternal for QuickJSValueInternal {
         const NAME_PROP_STR: &[u8] = b"name\0";
         const MESSAGE_PROP_STR: &[u8] = b"message\0";
 
-        let name_ident = unsafe { CStr::from_ptr(NAME_PROP_STR.as_ptr() as *const i8) }.to_owned();
+        let name_ident =
+            unsafe { CStr::from_ptr(NAME_PROP_STR.as_ptr() as *const c_char) }.to_owned();
         let message_ident =
-            unsafe { CStr::from_ptr(MESSAGE_PROP_STR.as_ptr() as *const i8) }.to_owned();
+            unsafe { CStr::from_ptr(MESSAGE_PROP_STR.as_ptr() as *const c_char) }.to_owned();
 
-        err.set_property(ctx.into(), &name_ident, name_jsv)
+        err.set_property(ctx, &name_ident, name_jsv)
             .unwrap_or_else(|_| {});
 
-        err.set_property(ctx.into(), &message_ident, message_jsv)
+        err.set_property(ctx, &message_ident, message_jsv)
             .unwrap_or_else(|_| {});
 
         name_jsv.release(ctx);
@@ -113,56 +193,66 @@ impl JSValueInternal for QuickJSValueInternal {
         err
     }
 
-    fn native_prototype_for<T: crate::JSExportClass>(
+    fn native_prototype_for<'r: 'c, 'c, T: JSExportClass>(
         ctx: Self::ContextType,
-        runtime: <Self::ContextType as JSContextInternal>::RuntimeType,
+        _: &<Self::ContextType as JSContextImplementation>::RuntimeType,
     ) -> EsperantoResult<Self> {
         let class_id = get_or_create_class_id::<T>(ctx)?;
 
-        // let instance = unsafe { JS_GetClassProto(*ctx, info.instance_class) };
+        // JS_GetClassProto returns a retained value so we don't need to retain
+        // it again ourselves
         Ok(unsafe { JS_GetClassProto(*ctx, class_id) })
+    }
+
+    fn new_native_function(
+        func: Box<NativeFunction>,
+        ctx: Self::ContextType,
+        runtime: &<Self::ContextType as JSContextImplementation>::RuntimeType,
+    ) -> EsperantoResult<Self> {
+        let holder = Self::from_native_class(NativeFunctionHolder(func), ctx, runtime)?;
+
+        // JS_NewCFunctionData retains the data values itself
+        let mut data = [holder];
+        let function = check_quickjs_exception!(ctx => unsafe {
+            JS_NewCFunctionData(*ctx, Some(native_function_call), 0, 0, 1, data.as_mut_ptr())
+        });
+        holder.release(ctx);
 
-        // Ok(info.prototype)
+        function
     }
 
     fn from_native_class<T: JSExportClass>(
         instance: T,
         ctx: Self::ContextType,
-        runtime: <Self::ContextType as JSContextInternal>::RuntimeType,
+        _: &<Self::ContextType as JSContextImplementation>::RuntimeType,
     ) -> EsperantoResult<Self> {
-        // let info = <T as QuickJSExportExtensions>::get_or_create_class_prototype(*ctx, runtime)?;
         let class_id = get_or_create_class_id::<T>(ctx)?;
         let obj = unsafe { JS_NewObjectClass(*ctx, class_id as _) };
-        // let obj = unsafe { JS_NewObjectProtoClass(*ctx, info.prototype, info.instance_class) };
         let ptr = JSExportPrivateData::from_instance(instance);
         unsafe { JS_SetOpaque(obj, ptr) };
 
         Ok(obj)
-        // let storage = get_classid_storage(runtime)?;
-        // let class_id = get_class_id::<T>(runtime, storage)?;
-        // let proto = get_or_create_class_prototype::<T>(class_id, *ctx)?;
-
-        // // Then we create a new object with this prototype:
-        // let new_object = unsafe { JS_NewObjectProtoClass(*ctx, proto, class_id) };
-
-        // proto.release(ctx);
-
-        // let boxed = Box::new(instance);
-        // let ptr = Box::into_raw(boxed) as *mut c_void;
-        // unsafe { JS_SetOpaque(new_object, ptr) };
-        // Ok(new_object)
     }
 
-    fn get_native_ref<'a, T: JSExportClass>(
+    fn get_native_ptr<T: JSExportClass>(
         self,
-        ctx: Self::ContextType,
-    ) -> EsperantoResult<&'a T> {
-        // let info =
-        // <T as QuickJSExportExtensions>::get_or_create_class_prototype(*ctx, ctx.get_runtime())?;
-        let class_id = get_or_create_class_id::<T>(ctx)?;
+        _: Self::ContextType,
+        runtime: &<Self::ContextType as JSContextImplementation>::RuntimeType,
+    ) -> EsperantoResult<*mut c_void> {
+        // If the class hasn't been created yet there's no way this value can be an instance
+        // of it, so there's no reason to create it here.
+        let runtime = *runtime;
+        let class_id = get_existing_class_id::<T>(runtime)?
+            .ok_or(JSExportError::CouldNotGetNativeObject(T::CLASS_NAME))?;
         let ptr = unsafe { JS_GetOpaque(self, class_id) };
+        if ptr.is_null() == false {
+            return Ok(ptr);
+        }
 
-        JSExportPrivateData::<T>::data_from_ptr(ptr)
+        // JS_GetOpaque only succeeds when given the exact class of the object, and QuickJS
+        // won't tell us what that is. So if it isn't a T we have to check every other class
+        // we know about in case it's a subclass.
+        Ok(find_opaque_in_any_class(self, runtime))
     }
 
     fn set_property(
@@ -193,19 +283,157 @@ impl JSValueInternal for QuickJSValueInternal {
         ctx: Self::ContextType,
         name: &std::ffi::CStr,
     ) -> Result<Self, crate::EsperantoError> {
-        Ok(unsafe { JS_GetPropertyStr(*ctx, self, name.as_ptr()) }.into())
+        check_quickjs_exception!(ctx => {
+            unsafe { JS_GetPropertyStr(*ctx, self, name.as_ptr()) }
+        })
+    }
+
+    fn get_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+    ) -> EsperantoResult<Self> {
+        if let PropertyKey::Index(index) = key {
+            return self.get_property_at_index(ctx, index);
+        }
+
+        let atom = new_atom_for_key(key, ctx)?;
+        let result = check_quickjs_exception!(ctx => {
+            unsafe { JS_GetPropertyInternal(*ctx, self, atom, self, 0) }
+        });
+        unsafe { JS_FreeAtom(*ctx, atom) };
+        result
+    }
+
+    fn set_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+        new_value: Self,
+    ) -> EsperantoResult<()> {
+        if let PropertyKey::Index(index) = key {
+            return self.set_property_at_index(ctx, index, new_value);
+        }
+
+        if unsafe { JS_IsObject__(self) } == 0 {
+            return Err(JSValueError::IsNotAnObject.into());
+        }
+
+        let atom = new_atom_for_key(key, ctx)?;
+        // Same as set_property, this frees the value it's given
+        let retained = new_value.retain(ctx);
+        let result = check_quickjs_exception!(ctx => {
+            unsafe { JS_SetPropertyInternal(*ctx, self, atom, retained, JS_PROP_THROW as i32) }
+        });
+        unsafe { JS_FreeAtom(*ctx, atom) };
+
+        result.map(|_| ())
     }
 
-    fn delete_property(self, ctx: Self::ContextType, name: &CStr) -> EsperantoResult<bool> {
-        let name = unsafe { JS_NewAtom(*ctx, name.as_ptr()) };
+    fn delete_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+    ) -> EsperantoResult<bool> {
+        let atom = new_atom_for_key(key, ctx)?;
         let result = check_quickjs_exception!(ctx => {
-            unsafe {JS_DeleteProperty(*ctx, self, name, 0) }
+            unsafe { JS_DeleteProperty(*ctx, self, atom, 0) }
+        });
+        unsafe { JS_FreeAtom(*ctx, atom) };
+
+        Ok(result? == 1)
+    }
+
+    fn new_object(ctx: Self::ContextType) -> EsperantoResult<Self> {
+        check_quickjs_exception!(ctx => {
+            unsafe { JS_NewObject(*ctx) }
+        })
+    }
+
+    fn own_property_names(self, ctx: Self::ContextType) -> EsperantoResult<Vec<Vec<u8>>> {
+        if unsafe { JS_IsObject__(self) } == 0 {
+            return Err(JSValueError::IsNotAnObject.into());
+        }
+
+        let mut properties: *mut JSPropertyEnum = std::ptr::null_mut();
+        let mut length: u32 = 0;
+        let flags = (JS_GPN_STRING_MASK | JS_GPN_ENUM_ONLY) as i32;
+        check_quickjs_exception!(ctx => {
+            unsafe { JS_GetOwnPropertyNames(*ctx, &mut properties, &mut length, self, flags) }
         })?;
-        unsafe { JS_FreeAtom(*ctx, name) };
 
+        // We own the array and every atom in it, so everything needs freeing even if one of
+        // the names fails to convert.
+        let mut names = Vec::with_capacity(length as usize);
+        for index in 0..length as usize {
+            let atom = unsafe { (*properties.add(index)).atom };
+            // Names can contain NUL and unpaired surrogates, so we read them as WTF-8 rather
+            // than with JS_AtomToCString
+            let name = check_quickjs_exception!(ctx => {
+                unsafe { JS_AtomToValue(*ctx, atom) }
+            })
+            .and_then(|value| {
+                let name = value.as_wtf8(ctx);
+                value.release(ctx);
+                name
+            });
+            names.push(name);
+            unsafe { JS_FreeAtom(*ctx, atom) };
+        }
+        unsafe { js_free(*ctx, properties as *mut c_void) };
+
+        names.into_iter().collect()
+    }
+
+    fn new_array(values: Vec<Self>, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        let array = check_quickjs_exception!(ctx => {
+            unsafe { JS_NewArray(*ctx) }
+        })?;
+
+        for (index, value) in values.into_iter().enumerate() {
+            if let Err(err) = array.set_property_at_index(ctx, index as u32, value) {
+                array.release(ctx);
+                return Err(err);
+            }
+        }
+
+        Ok(array)
+    }
+
+    fn is_array(self, ctx: Self::ContextType) -> EsperantoResult<bool> {
+        // Can throw if the value is a revoked proxy
+        let result = check_quickjs_exception!(ctx => {
+            unsafe { JS_IsArray(*ctx, self) }
+        })?;
         Ok(result == 1)
     }
 
+    fn get_property_at_index(self, ctx: Self::ContextType, index: u32) -> EsperantoResult<Self> {
+        check_quickjs_exception!(ctx => {
+            unsafe { JS_GetPropertyUint32(*ctx, self, index) }
+        })
+    }
+
+    fn set_property_at_index(
+        self,
+        ctx: Self::ContextType,
+        index: u32,
+        new_value: Self,
+    ) -> EsperantoResult<()> {
+        if unsafe { JS_IsObject__(self) } == 0 {
+            return Err(JSValueError::IsNotAnObject.into());
+        }
+
+        // Same as set_property, this frees the value it's given
+        let retained = new_value.retain(ctx);
+
+        check_quickjs_exception!(ctx => {
+            unsafe { JS_SetPropertyUint32(*ctx, self, index, retained) }
+        })?;
+
+        Ok(())
+    }
+
     fn new_function(
         function_text: &CString,
         argument_names: &Vec<CString>,
@@ -214,8 +442,9 @@ impl JSValueInternal for QuickJSValueInternal {
         static FUNCTION_NAME: &[u8] = b"Function\0";
         let global_obj = ctx.get_globalobject();
 
-        let func_constructor =
-            unsafe { JS_GetPropertyStr(*ctx, global_obj, FUNCTION_NAME.as_ptr() as *const i8) };
+        let func_constructor = unsafe {
+            JS_GetPropertyStr(*ctx, global_obj, FUNCTION_NAME.as_ptr() as *const c_char)
+        };
 
         let mut construct_arguments: Vec<Self> = argument_names
             .iter()
@@ -242,15 +471,11 @@ impl JSValueInternal for QuickJSValueInternal {
         }
         let argc = arguments.len() as i32;
         let mut argv: Vec<QuickJSValue> = arguments.iter().map(|a| *a).collect();
-        // for arg in &argv {
-        //     unsafe { JS_DupValue__(*ctx, *arg) };
-        // }
         let bound = bound_to.unwrap_or(Self::undefined(ctx));
         let ret_val = check_quickjs_exception!(ctx => {
             unsafe { JS_Call(*ctx, self, bound, argc, argv.as_mut_ptr()) }
         })?;
 
-        // unsafe { JS_DupValue__(*ctx, ret_val) };
         Ok(ret_val)
     }
 
@@ -259,10 +484,13 @@ impl JSValueInternal for QuickJSValueInternal {
         arguments: Vec<Self>,
         ctx: Self::ContextType,
     ) -> EsperantoResult<Self> {
+        if self.is_object(ctx) == false {
+            return Err(JSValueError::IsNotAnObject.into());
+        }
         let argc = arguments.len() as i32;
         let mut argv: Vec<QuickJSValue> = arguments.iter().map(|a| *a).collect();
         if unsafe { JS_IsConstructor(*ctx, self) } == 0 {
-            panic!("not constructor?")
+            return Err(JSValueError::MustUseNewWithConstuctor.into());
         }
         check_quickjs_exception!(ctx => {
             unsafe {JS_CallConstructor(*ctx, self, argc, argv.as_mut_ptr())}
@@ -277,16 +505,80 @@ impl JSValueInternal for QuickJSValueInternal {
         unsafe { JS_IsString__(self) == 1 }
     }
 
+    fn null(_: Self::ContextType) -> Self {
+        unsafe { JS_NULL__ }
+    }
+
+    fn is_undefined(self, _: Self::ContextType) -> bool {
+        unsafe { JS_IsUndefined__(self) == 1 }
+    }
+
+    fn is_null(self, _: Self::ContextType) -> bool {
+        unsafe { JS_GetTag__(self) == JS_TAG_NULL }
+    }
+
+    fn is_bool(self, _: Self::ContextType) -> bool {
+        unsafe { JS_GetTag__(self) == JS_TAG_BOOL }
+    }
+
+    fn is_number(self, _: Self::ContextType) -> bool {
+        // QuickJS stores small integers separately from floats, but they're both numbers to JS
+        let tag = unsafe { JS_GetTag__(self) };
+        tag == JS_TAG_INT || tag == JS_TAG_FLOAT64
+    }
+
+    fn is_function(self, ctx: Self::ContextType) -> bool {
+        unsafe { JS_IsFunction(*ctx, self) == 1 }
+    }
+
+    fn is_symbol(self, _: Self::ContextType) -> bool {
+        unsafe { JS_GetTag__(self) == JS_TAG_SYMBOL }
+    }
+
+    fn new_symbol(description: Option<&str>, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        // QuickJS has no public API for creating symbols, so we call the original Symbol function
+        let symbol_function = get_intrinsic(ctx, Intrinsic::Symbol)?;
+
+        let arguments = match description {
+            Some(description) => vec![Self::from_wtf8(description.as_bytes(), ctx)?],
+            None => vec![],
+        };
+        let result = symbol_function.call_as_function(arguments.clone(), None, ctx);
+
+        arguments.into_iter().for_each(|argument| argument.release(ctx));
+        result
+    }
+
+    fn intrinsic(intrinsic: Intrinsic, ctx: Self::ContextType) -> EsperantoResult<Self> {
+        get_intrinsic(ctx, intrinsic)
+    }
+
     fn equals(self, other: Self, _: Self::ContextType) -> bool {
         unsafe { JS_IsEqual__(self, other) == 1 }
     }
 
     fn is_instanceof(self, target: Self, ctx: Self::ContextType) -> EsperantoResult<bool> {
-        let constructor = target.get_property(ctx, CONSTRUCTOR_STRING)?;
+        if target.is_object(ctx) == false {
+            return Err(JSValueError::IsNotAnObject.into());
+        }
+
+        // Our exported classes use the prototype object itself as the thing you call
+        // and construct, so there isn't necessarily a constructor function with a .prototype
+        // for QuickJS to use. In that case we walk the prototype chain ourselves.
+        let prototype_of_target = target.get_property(ctx, unsafe {
+            CStr::from_ptr(PROTOTYPE_STRING.as_ptr() as *const c_char)
+        })?;
+        let target_is_constructor = unsafe { JS_IsFunction(*ctx, target) } == 1
+            && prototype_of_target.is_object(ctx);
+        prototype_of_target.release(ctx);
+
+        if target_is_constructor == false {
+            return Ok(has_in_prototype_chain(self, target, ctx));
+        }
+
         let result = check_quickjs_exception!(ctx => {
-            unsafe {JS_IsInstanceOf(*ctx,self, constructor)}
+            unsafe {JS_IsInstanceOf(*ctx, self, target)}
         })?;
-        unsafe { JS_FreeValue__(*ctx, constructor) };
         if result != 0 && result != 1 {
             // we got a result we aren't expecting but no exception was thrown
             return Err(EsperantoError::CatchExceptionError(Box::new(
@@ -300,45 +592,117 @@ impl JSValueInternal for QuickJSValueInternal {
         Ok(unsafe { JS_IsError(*ctx, self) == 1 })
     }
 
+    fn error_location(self, ctx: Self::ContextType) -> EsperantoResult<ErrorLocation> {
+        // QuickJS only sets fileName and lineNumber on syntax errors. For everything else the
+        // location is only available from the stack.
+        let file_name = unsafe { CStr::from_ptr(FILE_NAME_STRING.as_ptr() as *const c_char) };
+        if let Some(source_url) = self.get_optional_string_property(ctx, file_name)? {
+            let line_number =
+                unsafe { CStr::from_ptr(LINE_NUMBER_STRING.as_ptr() as *const c_char) };
+            return Ok(ErrorLocation {
+                source_url: Some(source_url),
+                line: self.get_optional_position_property(ctx, line_number)?,
+                column: None,
+            });
+        }
+
+        let stack = unsafe { CStr::from_ptr(STACK_STRING.as_ptr() as *const c_char) };
+        Ok(self
+            .get_optional_string_property(ctx, stack)?
+            .map(|stack| location_from_stack(&stack))
+            .unwrap_or_default())
+    }
+
     fn is_object(self, _: Self::ContextType) -> bool {
         unsafe { JS_IsObject__(self) == 1 }
     }
 
-    fn constructor_for<T: JSExportClass>(
-        ctx: Self::ContextType,
-        runtime: <Self::ContextType as JSContextInternal>::RuntimeType,
-    ) -> EsperantoResult<Self> {
-        let prototype = Self::native_prototype_for::<T>(ctx, runtime)?;
-        let c = prototype.get_property(ctx, CONSTRUCTOR_STRING);
-        prototype.release(ctx);
-        c
+    fn get_private_data(self, _: Self::ContextType) -> EsperantoResult<*mut c_void> {
+        // QuickJS only lets us attach opaque data to objects created with a custom class,
+        // and doesn't give us a way of finding out which class an object has. Our exported
+        // classes go through JS_GetOpaque directly so there's no need for this right now.
+        Err(JSValueError::CouldNotStorePrivateData.into())
     }
-}
 
-impl<'c> From<JSValue<'c>> for QuickJSValue {
-    fn from(val: JSValue<'c>) -> Self {
-        val.internal
+    fn set_private_data(self, _: Self::ContextType, _: *mut c_void) -> EsperantoResult<()> {
+        Err(JSValueError::CouldNotStorePrivateData.into())
     }
 }
 
-// #[cfg(test)]
-// mod test {
-//     use crate::JSContext;
-//     use quickjs_android_suitable_sys::*;
-//     #[test]
-//     fn wtf() {
-//         let ctx = JSContext::new().unwrap();
-//         let ctxptr = ctx.internal;
-
-//         // let obj = unsafe { JS_AtomToValue(*ctxptr, JS_ATOM_object) };
+fn has_in_prototype_chain(
+    value: QuickJSValueInternal,
+    target: QuickJSValueInternal,
+    ctx: QuickJSContextPointer,
+) -> bool {
+    let mut current = unsafe { JS_GetPrototype(*ctx, value) };
+    loop {
+        if unsafe { JS_GetTag__(current) } == JS_TAG_NULL || current.is_object(ctx) == false {
+            current.release(ctx);
+            return false;
+        }
+        if current.equals(target, ctx) {
+            current.release(ctx);
+            return true;
+        }
+        let next = unsafe { JS_GetPrototype(*ctx, current) };
+        current.release(ctx);
+        current = next;
+    }
+}
 
-//         let obj = ctx.evaluate("Object", None).unwrap().internal;
-//         let prop =
-//             unsafe { JS_GetPropertyInternal(*ctxptr, obj, JS_ATOM_Symbol_hasInstance, obj, 0) };
+impl<'r, 'c> From<JSValue<'r, 'c>> for QuickJSValue {
+    fn from(val: JSValue<'r, 'c>) -> Self {
+        val.internal
+    }
+}
 
-//         let tag = unsafe { JS_GetTag__(prop) };
+/// Atoms are QuickJS's interned property keys, this creates one for a PropertyKey. The caller
+/// is responsible for freeing it.
+fn new_atom_for_key(
+    key: PropertyKey<'_, '_, '_>,
+    ctx: QuickJSContextPointer,
+) -> EsperantoResult<JSAtom> {
+    // This returns JS_ATOM_NULL if converting the key threw, e.g. an object key with a
+    // throwing toString()
+    check_quickjs_exception!(ctx => match key {
+        PropertyKey::Index(index) => unsafe { JS_NewAtomUInt32(*ctx, index) },
+        PropertyKey::Symbol(value) => unsafe { JS_ValueToAtom(*ctx, value.internal) },
+        PropertyKey::Name(name) => {
+            // JS_NewAtomLen would be more direct but it looks up existing atoms by comparing
+            // bytes, which can confuse UTF-8 names with Latin-1 ones. Going via a string
+            // value avoids that and also turns names like "0" into index atoms, same as JS.
+            let name_value = QuickJSValueInternal::from_wtf8(name.as_bytes(), ctx)?;
+            let atom = unsafe { JS_ValueToAtom(*ctx, name_value) };
+            name_value.release(ctx);
+            atom
+        }
+    })
+}
 
-//         unsafe { JS_FreeValue__(*ctxptr, prop) };
-//         println!("{}", tag)
-//     }
-// }
+/// Finds the location of the first frame in a QuickJS stack trace that has one. Frames look
+/// like `    at functionName (file:line)`, or `    at functionName (native)` for C functions.
+fn location_from_stack(stack: &str) -> ErrorLocation {
+    stack
+        .lines()
+        .filter_map(|frame| {
+            let frame = frame.trim_end().strip_suffix(')')?;
+            let location = &frame[frame.rfind(" (")? + 2..];
+            if location == "native" {
+                return None;
+            }
+            let (source_url, line) = match location.rsplit_once(':') {
+                Some((file, line)) => match line.parse() {
+                    Ok(line) => (file, Some(line)),
+                    Err(_) => (location, None),
+                },
+                None => (location, None),
+            };
+            Some(ErrorLocation {
+                source_url: Some(source_url.to_string()),
+                line,
+                column: None,
+            })
+        })
+        .next()
+        .unwrap_or_default()
+}
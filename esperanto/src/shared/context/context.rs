@@ -1,12 +1,18 @@
 use std::ffi::CString;
 use std::marker::PhantomData;
 
+use super::intrinsics::Intrinsics;
+use super::thrown_values::ThrownValues;
 use super::{context_error::JSContextError, evaluate_metadata::EvaluateMetadata};
 use crate::shared::engine_impl::ActiveJSContextImplementation;
+use crate::shared::errors::EsperantoError;
 use crate::shared::util::StoredOrReferenced;
 use crate::shared::value::ValueResult;
 use crate::shared::{context::JSContextImplementation, errors::EsperantoResult};
-use crate::shared::{runtime::JSRuntime, value::JSValue};
+use crate::shared::{
+    runtime::JSRuntime,
+    value::{JSValue, JSValueImplementation},
+};
 use crate::Retain;
 
 type StoredOrReferencedRuntime<'r> = StoredOrReferenced<'r, JSRuntime<'r>>;
@@ -21,6 +27,10 @@ pub struct JSContext<'r, 'c> {
     // The engine-specific implementation of JSContext
     pub(super) implementation: ActiveJSContextImplementation,
     pub(super) runtime: StoredOrReferencedRuntime<'r>,
+    // Exceptions thrown in this context, kept so errors can refer back to them
+    pub(super) thrown_values: ThrownValues,
+    // Built-ins captured before any script could replace them
+    pub(super) intrinsics: Intrinsics,
     // Our actual implementation has no lifetime, we're constructing
     // one manually. So we use PhantomData to store that lifetime.
     _lifetime: &'c PhantomData<()>,
@@ -38,6 +48,8 @@ where
         let ctx = JSContext {
             implementation,
             runtime: runtime.into(),
+            thrown_values: ThrownValues::new(),
+            intrinsics: Intrinsics::capture(implementation),
             _lifetime: &PhantomData,
         };
 
@@ -74,6 +86,7 @@ where
         let len = script.len();
         let cstr = CString::new(script).map_err(|_| JSContextError::CouldNotParseScript)?;
 
+        self.thrown_values.release_dropped(self.implementation());
         self.implementation()
             .evaluate(cstr, len, metadata)
             .map(|internal| {
@@ -82,6 +95,17 @@ where
             })
     }
 
+    /// Parse a JSON string with the engine's built-in parser. Much faster (and less fragile) than
+    /// escaping the string and passing it to `JSON.parse()` via `evaluate`.
+    ///
+    /// # Arguments
+    /// * `json`: The JSON to parse. Invalid JSON results in a SyntaxError.
+    pub fn parse_json(&'c self, json: &str) -> ValueResult<'r, 'c> {
+        self.implementation()
+            .parse_json(json)
+            .map(|internal| Retain::wrap(JSValue::wrap_internal(internal, self)))
+    }
+
     pub(crate) fn implementation(&self) -> ActiveJSContextImplementation {
         self.implementation
     }
@@ -107,6 +131,19 @@ where
     pub fn get_runtime(&'c self) -> &JSRuntime {
         &self.runtime
     }
+
+    /// Get back the exact value that was thrown to cause an error, whether it was an Error
+    /// object or something else entirely (e.g. `throw {code: 42}`). Returns None if the
+    /// error didn't come from JS or was thrown in a different context.
+    pub fn thrown_value(&'c self, error: &EsperantoError) -> Option<Retain<JSValue<'r, 'c>>> {
+        let handle = match error {
+            EsperantoError::JavaScriptError(err) => err.thrown_value.as_ref()?,
+            _ => return None,
+        };
+        let raw = self.thrown_values.get(handle)?;
+        let retained = raw.retain(self.implementation());
+        Some(Retain::wrap(JSValue::wrap_internal(retained, self)))
+    }
 }
 
 /// Internal functions
@@ -125,6 +162,8 @@ where
 
 impl Drop for JSContext<'_, '_> {
     fn drop(&mut self) {
+        self.thrown_values.release_all(self.implementation());
+        self.intrinsics.release_all(self.implementation());
         self.implementation().release()
     }
 }
@@ -11,4 +11,6 @@ pub enum JSContextError {
     RetainingWithWrongContext,
     #[error("Could not get internal context representation")]
     CouldNotGetInternalRepresentation,
+    #[error("The built-in {0} isn't available in this context")]
+    MissingIntrinsic(String),
 }
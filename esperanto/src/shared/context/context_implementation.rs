@@ -15,6 +15,7 @@ pub(crate) trait JSContextImplementation: Sized {
         script_size: usize,
         metadata: Option<&EvaluateMetadata>,
     ) -> Result<Self::ValueType, EsperantoError>;
+    fn parse_json(self, json: &str) -> EsperantoResult<Self::ValueType>;
     fn release(self);
     // fn get_runtime(self) -> Self::RuntimeType;
     fn get_globalobject(self) -> Self::ValueType;
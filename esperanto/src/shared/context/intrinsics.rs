@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fmt::{Debug, Formatter};
+
+use super::{JSContextError, JSContextImplementation};
+use crate::shared::engine_impl::{ActiveJSContextImplementation, JSValueInternalImpl};
+use crate::shared::errors::EsperantoResult;
+use crate::shared::value::{JSValueImplementation, WellKnownSymbol};
+use crate::JSContext;
+
+/// Built-in values we rely on internally. Scripts are free to replace the globals these come
+/// from (e.g. `globalThis.Symbol = {}`), so we grab them when the context is created, before
+/// any script has run, rather than looking them up whenever we need them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Intrinsic {
+    BigInt,
+    FunctionPrototype,
+    ObjectDefineProperty,
+    ObjectHasOwnProperty,
+    ObjectSetPrototypeOf,
+    Proxy,
+    ReflectConstruct,
+    String,
+    Symbol,
+    WellKnownSymbol(WellKnownSymbol),
+}
+
+impl Intrinsic {
+    fn all() -> Vec<Intrinsic> {
+        let mut all = vec![
+            Intrinsic::BigInt,
+            Intrinsic::FunctionPrototype,
+            Intrinsic::ObjectDefineProperty,
+            Intrinsic::ObjectHasOwnProperty,
+            Intrinsic::ObjectSetPrototypeOf,
+            Intrinsic::Proxy,
+            Intrinsic::ReflectConstruct,
+            Intrinsic::String,
+            Intrinsic::Symbol,
+        ];
+        all.extend(
+            WellKnownSymbol::ALL
+                .iter()
+                .map(|s| Intrinsic::WellKnownSymbol(*s)),
+        );
+        all
+    }
+
+    /// The expression that evaluates to this value in a fresh context
+    fn source(self) -> String {
+        match self {
+            Intrinsic::BigInt => "BigInt".to_string(),
+            Intrinsic::FunctionPrototype => "Function.prototype".to_string(),
+            Intrinsic::ObjectDefineProperty => "Object.defineProperty".to_string(),
+            Intrinsic::ObjectHasOwnProperty => "Object.prototype.hasOwnProperty".to_string(),
+            Intrinsic::ObjectSetPrototypeOf => "Object.setPrototypeOf".to_string(),
+            Intrinsic::Proxy => "Proxy".to_string(),
+            Intrinsic::ReflectConstruct => "Reflect.construct".to_string(),
+            Intrinsic::String => "String".to_string(),
+            Intrinsic::Symbol => "Symbol".to_string(),
+            Intrinsic::WellKnownSymbol(symbol) => format!("Symbol.{}", symbol.property_name()),
+        }
+    }
+}
+
+pub(crate) struct Intrinsics {
+    values: HashMap<Intrinsic, JSValueInternalImpl>,
+}
+
+impl Intrinsics {
+    /// Has to be called before any other script runs in the context. Evaluating each one
+    /// gives us a retained value, which we hold on to until the context is dropped.
+    pub(crate) fn capture(ctx: ActiveJSContextImplementation) -> Self {
+        let mut values = HashMap::new();
+        for intrinsic in Intrinsic::all() {
+            let source = intrinsic.source();
+            let len = source.len();
+            let script = match CString::new(source) {
+                Ok(script) => script,
+                Err(_) => continue,
+            };
+            // Not every engine has every built-in, those just fail when they're asked for
+            if let Ok(value) = ctx.evaluate(script, len, None) {
+                values.insert(intrinsic, value);
+            }
+        }
+        Intrinsics { values }
+    }
+
+    /// The captured value. It isn't retained, so it's only valid while the context is.
+    pub(crate) fn get(&self, intrinsic: Intrinsic) -> EsperantoResult<JSValueInternalImpl> {
+        self.values
+            .get(&intrinsic)
+            .copied()
+            .ok_or_else(|| JSContextError::MissingIntrinsic(intrinsic.source()).into())
+    }
+
+    pub(crate) fn release_all(&self, ctx: ActiveJSContextImplementation) {
+        for value in self.values.values() {
+            value.release(ctx);
+        }
+    }
+}
+
+impl Debug for Intrinsics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Intrinsics")
+            .field("count", &self.values.len())
+            .finish()
+    }
+}
+
+// Like ThrownValues, each context has its own
+impl PartialEq for Intrinsics {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Intrinsics {}
+
+/// For engine code that only has the raw context to hand.
+pub(crate) fn get_intrinsic(
+    ctx: ActiveJSContextImplementation,
+    intrinsic: Intrinsic,
+) -> EsperantoResult<JSValueInternalImpl> {
+    JSContext::borrow_from_implementation(ctx)?
+        .intrinsics
+        .get(intrinsic)
+}
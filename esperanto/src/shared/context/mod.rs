@@ -2,8 +2,12 @@ mod context;
 mod context_error;
 mod context_implementation;
 mod evaluate_metadata;
+mod intrinsics;
+mod thrown_values;
 
 pub use context::JSContext;
 pub use context_error::JSContextError;
 pub(crate) use context_implementation::JSContextImplementation;
 pub use evaluate_metadata::EvaluateMetadata;
+pub(crate) use intrinsics::{get_intrinsic, Intrinsic};
+pub(crate) use thrown_values::error_from_exception;
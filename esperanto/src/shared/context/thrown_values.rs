@@ -0,0 +1,148 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::shared::engine_impl::{ActiveJSContextImplementation, JSValueInternalImpl};
+use crate::shared::errors::{EsperantoError, JavaScriptError, ThrownValue};
+use crate::shared::value::JSValueImplementation;
+use crate::JSContext;
+
+// Shared between all contexts so a ThrownValue can't match a value in the wrong one
+static NEXT_THROWN_VALUE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The values behind the ThrownValue handles of a JSContext. Each one stays retained until
+/// its handle is dropped (we release it the next time the context evaluates or stores a value)
+/// or the context is.
+pub(crate) struct ThrownValues {
+    values: RefCell<HashMap<u64, JSValueInternalImpl>>,
+    dropped_ids: Arc<Mutex<Vec<u64>>>,
+}
+
+impl ThrownValues {
+    pub(crate) fn new() -> Self {
+        ThrownValues {
+            values: RefCell::new(HashMap::new()),
+            dropped_ids: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Takes ownership of an already retained value.
+    pub(crate) fn store(
+        &self,
+        value: JSValueInternalImpl,
+        ctx: ActiveJSContextImplementation,
+    ) -> ThrownValue {
+        // Also catch up on any handles dropped since the last evaluate
+        self.release_dropped(ctx);
+
+        let id = NEXT_THROWN_VALUE_ID.fetch_add(1, Ordering::Relaxed);
+        self.values.borrow_mut().insert(id, value);
+        ThrownValue::new(id, Arc::downgrade(&self.dropped_ids))
+    }
+
+    /// The stored value, if the handle belongs to this context. It isn't retained.
+    pub(crate) fn get(&self, handle: &ThrownValue) -> Option<JSValueInternalImpl> {
+        self.values.borrow().get(&handle.id).copied()
+    }
+
+    /// Handles can be dropped anywhere, so the context calls this whenever it's about to run
+    /// code to catch up on releasing their values.
+    pub(crate) fn release_dropped(&self, ctx: ActiveJSContextImplementation) {
+        let dropped_ids: Vec<u64> = match self.dropped_ids.lock() {
+            Ok(mut dropped_ids) => dropped_ids.drain(..).collect(),
+            Err(_) => return,
+        };
+        let mut values = self.values.borrow_mut();
+        for id in dropped_ids {
+            if let Some(value) = values.remove(&id) {
+                value.release(ctx);
+            }
+        }
+    }
+
+    /// Called when the context is dropped. Any handles still around just stop resolving.
+    pub(crate) fn release_all(&self, ctx: ActiveJSContextImplementation) {
+        for (_, value) in self.values.borrow_mut().drain() {
+            value.release(ctx);
+        }
+    }
+}
+
+impl Debug for ThrownValues {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThrownValues")
+            .field("count", &self.values.borrow().len())
+            .finish()
+    }
+}
+
+// Each context has its own store, so two are only ever equal if they're the same one
+impl PartialEq for ThrownValues {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for ThrownValues {}
+
+const MAX_SUMMARY_DEPTH: usize = 8;
+
+thread_local! {
+    static SUMMARY_DEPTH: Cell<usize> = Cell::new(0);
+}
+
+/// Turns a caught exception into an EsperantoError, keeping the original value around in
+/// the context it was thrown in. Takes ownership of the (retained) exception.
+pub(crate) fn error_from_exception(
+    exception: JSValueInternalImpl,
+    ctx: ActiveJSContextImplementation,
+) -> EsperantoError {
+    // Summarising the exception can run getters, which can throw exceptions of their own that
+    // end up back in here. Past a certain depth we stop summarising so that something like
+    // `throw {get message() { throw this }}` can't recurse forever.
+    let depth = SUMMARY_DEPTH.with(|depth| depth.replace(depth.get() + 1));
+    let mut error = match depth < MAX_SUMMARY_DEPTH {
+        true => exception.to_js_error(ctx),
+        false => JavaScriptError::new(String::new(), String::new()),
+    };
+    SUMMARY_DEPTH.with(|summary_depth| summary_depth.set(depth));
+
+    // We can't store it if the context is still being set up
+    match JSContext::borrow_from_implementation(ctx) {
+        Ok(context) => error.thrown_value = Some(context.thrown_values.store(exception, ctx)),
+        Err(_) => exception.release(ctx),
+    }
+
+    EsperantoError::JavaScriptError(error)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::JSContext;
+
+    #[test]
+    fn releases_values_once_their_handles_are_dropped() {
+        let ctx = JSContext::new().unwrap();
+        let first = ctx.evaluate("throw 1", None).unwrap_err();
+        let second = ctx.evaluate("throw 2", None).unwrap_err();
+        assert_eq!(ctx.thrown_values.values.borrow().len(), 2);
+
+        drop(first);
+        drop(second);
+        let _third = ctx.evaluate("throw 3", None).unwrap_err();
+        assert_eq!(ctx.thrown_values.values.borrow().len(), 1);
+    }
+
+    #[test]
+    fn releases_dropped_values_when_evaluating() {
+        let ctx = JSContext::new().unwrap();
+        let error = ctx.evaluate("throw 1", None).unwrap_err();
+        assert_eq!(ctx.thrown_values.values.borrow().len(), 1);
+
+        drop(error);
+        ctx.evaluate("1", None).unwrap();
+        assert_eq!(ctx.thrown_values.values.borrow().len(), 0);
+    }
+}
@@ -1,7 +1,9 @@
-use std::{ffi::NulError, str::Utf8Error, num::TryFromIntError};
+use std::{ffi::NulError, str::Utf8Error};
 
 use thiserror::Error;
 
+use crate::shared::value::JSValueType;
+
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum ConversionError {
     #[error("Could not convert this native string into a JS-compatible one")]
@@ -13,6 +15,24 @@ pub enum ConversionError {
     #[error("Expected to receive an error but value is something else")]
     JSValueWasNotAnError,
 
-    #[error("Could not convert value into an integer")]
-    CouldNotConvertToInteger(#[from] TryFromIntError)
+    /// Holds the value we tried to convert, which was either fractional or out of range
+    #[error("Could not convert {0} into an integer of the requested type")]
+    CouldNotConvertToInteger(String),
+
+    /// Holds the value we tried to convert, which was finite but too large for an f32
+    #[error("Could not convert {0} into an f32 without overflowing")]
+    CouldNotConvertToF32(String),
+
+    /// Returned by strict conversions instead of coercing the value into the type we wanted
+    #[error("Expected a value of type {expected:?} but got {actual:?}")]
+    UnexpectedType {
+        expected: JSValueType,
+        actual: JSValueType,
+    },
+
+    #[error("Could not serialize this value into a JSValue: {0}")]
+    CouldNotSerialize(String),
+
+    #[error("Could not deserialize this JSValue: {0}")]
+    CouldNotDeserialize(String),
 }
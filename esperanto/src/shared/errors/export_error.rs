@@ -18,6 +18,9 @@ pub enum JSExportError {
     )]
     CouldNotGetNativeObject(&'static str),
 
+    #[error("The native {0} object is already borrowed in a way that prevents this borrow")]
+    NativeObjectAlreadyBorrowed(&'static str),
+
     #[error("Something happened that we're not expecting at all")]
     UnexpectedBehaviour,
 }
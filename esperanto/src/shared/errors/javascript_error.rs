@@ -2,23 +2,69 @@ use std::fmt::{Display, Formatter};
 
 use thiserror::Error;
 
+use super::ThrownValue;
+
 /// JavaScriptError is just a small wrapper for JavaScript error objects. By extracting them
 /// from the JS runtime we avoid lifetime and retain issues which makes error handling easier.
-#[derive(Debug, PartialEq, Eq, Error)]
+#[derive(Debug, Error)]
 pub struct JavaScriptError {
     pub name: String,
     pub message: String,
-    // TODO: stack traces?
+    /// The error's `stack` property. The format isn't standardised so it differs between
+    /// engines.
+    pub stack: Option<String>,
+    /// Where the error was created, if the engine recorded it. QuickJS doesn't track columns
+    /// so `column` is always None there.
+    pub source_url: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// The error passed as `cause` when this one was created, if any. Also returned by
+    /// `Error::source`, so the whole chain can be walked.
+    #[source]
+    pub cause: Option<Box<JavaScriptError>>,
+    /// The value that was actually thrown, which might not be an Error at all (e.g.
+    /// `throw {code: 42}`). None for errors created in Rust. Returning an error that still
+    /// has this set from a native function rethrows the original value unchanged.
+    pub thrown_value: Option<ThrownValue>,
 }
 
 impl JavaScriptError {
     pub fn new(name: String, message: String) -> Self {
-        JavaScriptError { name, message }
+        JavaScriptError {
+            name,
+            message,
+            stack: None,
+            source_url: None,
+            line: None,
+            column: None,
+            cause: None,
+            thrown_value: None,
+        }
     }
 }
 
+// Leaves out thrown_value: each handle is unique, so two errors with the same details would
+// otherwise never be equal just because they were thrown separately
+impl PartialEq for JavaScriptError {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.message == other.message
+            && self.stack == other.stack
+            && self.source_url == other.source_url
+            && self.line == other.line
+            && self.column == other.column
+            && self.cause == other.cause
+    }
+}
+
+impl Eq for JavaScriptError {}
+
 impl Display for JavaScriptError {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        // Thrown values that aren't errors (e.g. `throw "oops"`) have no name
+        if self.name.is_empty() {
+            return write!(fmt, "{}", self.message);
+        }
         write!(fmt, "{}: {}", self.name, self.message)
     }
 }
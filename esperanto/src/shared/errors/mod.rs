@@ -3,10 +3,12 @@ mod conversion_error;
 mod esperanto_error;
 mod export_error;
 mod javascript_error;
+mod thrown_value;
 
 pub use catch_exception_error::CatchExceptionError;
 pub use conversion_error::ConversionError;
 pub use esperanto_error::{EsperantoError, EsperantoResult};
 pub use export_error::JSExportError;
 pub use javascript_error::JavaScriptError;
+pub use thrown_value::ThrownValue;
 // pub(crate) use jsvalue_to_error::jsvalue_to_error;
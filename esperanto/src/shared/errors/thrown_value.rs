@@ -0,0 +1,43 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::{Mutex, Weak};
+
+/// A handle to the exact value that was thrown in JS, which stays retained in the JSContext
+/// it was thrown in for as long as the handle exists. It has no lifetime of its own so errors
+/// can be passed around freely; use `JSContext::thrown_value` to get the JSValue back.
+pub struct ThrownValue {
+    pub(crate) id: u64,
+    // The context releases the value once it sees the id here. It's done that way rather
+    // than releasing it directly because the handle might be dropped after the context has
+    // gone, or on a different thread.
+    dropped_ids: Weak<Mutex<Vec<u64>>>,
+}
+
+impl ThrownValue {
+    pub(crate) fn new(id: u64, dropped_ids: Weak<Mutex<Vec<u64>>>) -> Self {
+        ThrownValue { id, dropped_ids }
+    }
+}
+
+impl Drop for ThrownValue {
+    fn drop(&mut self) {
+        if let Some(dropped_ids) = self.dropped_ids.upgrade() {
+            if let Ok(mut dropped_ids) = dropped_ids.lock() {
+                dropped_ids.push(self.id)
+            }
+        }
+    }
+}
+
+impl Debug for ThrownValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThrownValue").field("id", &self.id).finish()
+    }
+}
+
+impl PartialEq for ThrownValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for ThrownValue {}
@@ -1,12 +1,21 @@
 use crate::{
     shared::value::JSValueImplementation, EsperantoResult, JSExportClass, JSValue, Retain,
 };
-use std::ops::Deref;
+
+use super::JSExportPrivateData;
+use std::{
+    cell::{Ref, RefMut},
+    ops::{Deref, DerefMut},
+};
+
+// Both wrappers hold a borrow of the native object's RefCell alongside a retained reference to
+// the JSValue that owns it. The borrow has to be declared first so that it's dropped before
+// the value is released: releasing might finalize the object and free the RefCell.
 
 #[derive(Debug)]
 pub struct Js<'r, 'c: 'r, T: JSExportClass> {
+    reference: Ref<'c, T>,
     value: Retain<JSValue<'r, 'c>>,
-    reference: &'c T,
 }
 
 impl<'r, 'c, T> Js<'r, 'c, T>
@@ -15,13 +24,16 @@ where
     'c: 'r,
 {
     pub(crate) fn new(wrapping: Retain<JSValue<'r, 'c>>) -> EsperantoResult<Self> {
-        let re = wrapping
-            .internal
-            .get_native_ref::<T>(wrapping.context.implementation())?;
+        let context = wrapping.context;
+        let ptr = wrapping.internal.get_native_ptr::<T>(
+            context.implementation(),
+            context.get_runtime().implementation(),
+        )?;
+        let re = JSExportPrivateData::<T>::borrow_from_ptr(ptr)?;
 
         let created: Js<T> = Js {
-            value: wrapping,
             reference: re,
+            value: wrapping,
         };
 
         Ok(created)
@@ -40,7 +52,7 @@ where
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        self.reference
+        &self.reference
     }
 }
 
@@ -53,3 +65,58 @@ where
         self.deref()
     }
 }
+
+/// The mutable equivalent of Js, returned by `JSValue::as_native_mut`. While it exists any
+/// other attempt to borrow the same native object (e.g. by a re-entrant call from JS) will
+/// fail with `JSExportError::NativeObjectAlreadyBorrowed`.
+#[derive(Debug)]
+pub struct JsMut<'r, 'c: 'r, T: JSExportClass> {
+    reference: RefMut<'c, T>,
+    value: Retain<JSValue<'r, 'c>>,
+}
+
+impl<'r, 'c, T> JsMut<'r, 'c, T>
+where
+    T: JSExportClass,
+    'c: 'r,
+{
+    pub(crate) fn new(wrapping: Retain<JSValue<'r, 'c>>) -> EsperantoResult<Self> {
+        let context = wrapping.context;
+        let ptr = wrapping.internal.get_native_ptr::<T>(
+            context.implementation(),
+            context.get_runtime().implementation(),
+        )?;
+        let re = JSExportPrivateData::<T>::borrow_mut_from_ptr(ptr)?;
+
+        Ok(JsMut {
+            reference: re,
+            value: wrapping,
+        })
+    }
+
+    pub fn get_jsvalue(instance: &Self) -> &JSValue<'r, 'c> {
+        &instance.value
+    }
+}
+
+impl<'r, 'c, T> Deref for JsMut<'r, 'c, T>
+where
+    T: JSExportClass,
+    'c: 'r,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.reference
+    }
+}
+
+impl<'r, 'c, T> DerefMut for JsMut<'r, 'c, T>
+where
+    T: JSExportClass,
+    'c: 'r,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.reference
+    }
+}
@@ -1,7 +1,13 @@
-use std::{any::TypeId, ffi::c_void};
+use std::{
+    any::{Any, TypeId},
+    cell::{Ref, RefCell, RefMut},
+    ffi::c_void,
+};
 
 use crate::{shared::errors::JSExportError, EsperantoResult, JSExportClass};
 
+use super::{is_same_or_ancestor, upcast_to, upcast_to_mut, JSExportParent};
+
 // We use repr(C) here for not FFI reasons but because it guarantees the order of
 // the fields in a struct. When we grab this from JSValue private storage we can't
 // actually guarantee it's of type T (hence the methods around that). But if we make
@@ -13,7 +19,13 @@ pub(crate) struct JSExportPrivateData<T> {
     /// Used when creating error messages
     class_name: &'static str,
     type_id: TypeId,
-    pub(crate) data: T,
+    /// The parent of the stored class, so we can hand out references to ancestor types
+    parent: Option<JSExportParent>,
+    /// Returns the data field without needing to know its concrete type
+    as_any: fn(*mut c_void) -> *const RefCell<dyn Any>,
+    /// Wrapped in a RefCell so that we can hand out mutable references without worrying
+    /// about a re-entrant call from JS creating another one at the same time.
+    pub(crate) data: RefCell<T>,
 }
 
 impl<'r: 'c, 'c, T: JSExportClass> JSExportPrivateData<T> {
@@ -21,20 +33,55 @@ impl<'r: 'c, 'c, T: JSExportClass> JSExportPrivateData<T> {
         let wrapped = JSExportPrivateData {
             class_name: T::CLASS_NAME,
             type_id: TypeId::of::<T>(),
-            data: instance,
+            parent: T::PARENT,
+            as_any: Self::data_as_any,
+            data: RefCell::new(instance),
         };
         let boxed = Box::new(wrapped);
         Box::into_raw(boxed) as _
     }
 
-    pub(crate) fn data_from_ptr<'a>(raw_pointer: *mut c_void) -> EsperantoResult<&'a T> {
-        let as_ref = unsafe { (raw_pointer as *mut Self).as_ref() }
+    fn data_as_any(raw_pointer: *mut c_void) -> *const RefCell<dyn Any> {
+        unsafe { &(*(raw_pointer as *mut Self)).data }
+    }
+
+    // Grabs the stored data as a RefCell of unknown type, after checking that it's either a T
+    // or a subclass of T.
+    fn any_from_ptr<'a>(
+        raw_pointer: *mut c_void,
+    ) -> EsperantoResult<(&'a RefCell<dyn Any>, Option<JSExportParent>)> {
+        // The type of the data doesn't change the position of any of the preceding fields
+        // so we can read them before we know what it actually is.
+        let as_ref = unsafe { (raw_pointer as *mut JSExportPrivateData<()>).as_ref() }
             .ok_or(JSExportError::CouldNotGetNativeObject(T::CLASS_NAME))?;
 
-        if as_ref.type_id != TypeId::of::<T>() {
+        if is_same_or_ancestor::<T>(as_ref.type_id, as_ref.parent) == false {
             return Err(JSExportError::CouldNotGetNativeObject(T::CLASS_NAME).into());
         }
-        return Ok(&as_ref.data);
+        let data = unsafe { &*(as_ref.as_any)(raw_pointer) };
+        Ok((data, as_ref.parent))
+    }
+
+    pub(crate) fn borrow_from_ptr<'a>(raw_pointer: *mut c_void) -> EsperantoResult<Ref<'a, T>> {
+        let (data, parent) = Self::any_from_ptr(raw_pointer)?;
+        let borrowed = data
+            .try_borrow()
+            .map_err(|_| JSExportError::NativeObjectAlreadyBorrowed(T::CLASS_NAME))?;
+
+        Ref::filter_map(borrowed, |any| upcast_to::<T>(any, parent))
+            .map_err(|_| JSExportError::CouldNotGetNativeObject(T::CLASS_NAME).into())
+    }
+
+    pub(crate) fn borrow_mut_from_ptr<'a>(
+        raw_pointer: *mut c_void,
+    ) -> EsperantoResult<RefMut<'a, T>> {
+        let (data, parent) = Self::any_from_ptr(raw_pointer)?;
+        let borrowed = data
+            .try_borrow_mut()
+            .map_err(|_| JSExportError::NativeObjectAlreadyBorrowed(T::CLASS_NAME))?;
+
+        RefMut::filter_map(borrowed, |any| upcast_to_mut::<T>(any, parent))
+            .map_err(|_| JSExportError::CouldNotGetNativeObject(T::CLASS_NAME).into())
     }
 
     pub(crate) fn drop(raw_pointer: *mut c_void) {
@@ -1,7 +1,12 @@
+use std::any::{Any, TypeId};
+
 use crate::{shared::errors::EsperantoResult, JSContext, JSValue, Retain};
 
 pub enum JSExportAttribute {
-    Function(JSClassFunction),
+    Function(JSClassMethod),
+    /// An accessor property on the class prototype. Both functions receive the `this` value
+    /// the property was accessed on, the setter also receives the new value. If no setter is
+    /// provided the property is read-only, so assigning to it throws a TypeError in strict mode.
     Property {
         getter: for<'r, 'c, 'v> fn(
             &'c JSContext<'r, 'c>,
@@ -9,12 +14,16 @@ pub enum JSExportAttribute {
         ) -> EsperantoResult<Retain<JSValue<'r, 'c>>>,
         setter: Option<
             for<'r, 'c, 'v> fn(
+                &'c JSContext<'r, 'c>,
                 &'v JSValue<'r, 'c>,
                 &'v JSValue<'r, 'c>,
-                &'c JSContext<'r, 'c>,
-            ) -> EsperantoResult<JSValue<'r, 'c>>,
+            ) -> EsperantoResult<()>,
         >,
     },
+    /// A data property whose value is created once, when the class is first used in a context.
+    /// Like built-in constants (e.g. `Number.MAX_SAFE_INTEGER`) it can't be assigned to,
+    /// deleted or enumerated.
+    Constant(for<'r, 'c> fn(&'c JSContext<'r, 'c>) -> EsperantoResult<Retain<JSValue<'r, 'c>>>),
 }
 
 pub struct JSClassFunction {
@@ -25,12 +34,151 @@ pub struct JSClassFunction {
     ) -> EsperantoResult<Retain<JSValue<'r, 'c>>>,
 }
 
+/// A function installed on the class prototype. Unlike JSClassFunction it receives the
+/// `this` value it was called with, so it can get at the native struct via `as_native`.
+pub struct JSClassMethod {
+    pub num_args: i32,
+    pub func: for<'r, 'c, 'v> fn(
+        &'c JSContext<'r, 'c>,
+        &'v JSValue<'r, 'c>,
+        &'v [&'v JSValue<'r, 'c>],
+    ) -> EsperantoResult<Retain<JSValue<'r, 'c>>>,
+}
+
 pub trait JSExportClass: 'static {
     const CLASS_NAME: &'static str;
     const ATTRIBUTES: JSExportAttributes = None;
+    /// Attributes installed on the constructor object (as returned by `JSValue::constructor_for`)
+    /// rather than the prototype. Methods and properties receive the constructor as `this`.
+    /// Static constants are declared with `Constant`.
+    const STATIC_ATTRIBUTES: JSExportAttributes = None;
     const CALL_AS_CONSTRUCTOR: Option<JSClassFunction> = None;
     const CALL_AS_FUNCTION: Option<JSClassFunction> = None;
+    /// The exported class this one inherits from, if any. Instances get the parent prototype
+    /// in their prototype chain (so `instanceof` and inherited attributes work) and can be
+    /// passed to `as_native` as the parent type.
+    const PARENT: Option<JSExportParent> = None;
+}
+
+/// Links a JSExportClass to its parent class. Created with `JSExportParent::of`, e.g.
+/// `const PARENT: Option<JSExportParent> = Some(JSExportParent::of::<Event, Self>());`
+///
+/// Rust structs can't inherit from each other so the child is expected to contain the
+/// parent's data and expose it via AsRef and AsMut. That's how we get from a child instance
+/// to its parent when someone asks for the parent type with `as_native`.
+#[derive(Clone, Copy)]
+pub struct JSExportParent {
+    pub(crate) type_id: fn() -> TypeId,
+    pub(crate) parent: fn() -> Option<JSExportParent>,
+    pub(crate) prototype:
+        for<'r, 'c> fn(&'c JSContext<'r, 'c>) -> EsperantoResult<Retain<JSValue<'r, 'c>>>,
+    upcast: fn(&dyn Any) -> Option<&dyn Any>,
+    upcast_mut: fn(&mut dyn Any) -> Option<&mut dyn Any>,
+}
+
+impl JSExportParent {
+    pub const fn of<Parent, Child>() -> Self
+    where
+        Parent: JSExportClass,
+        Child: JSExportClass + AsRef<Parent> + AsMut<Parent>,
+    {
+        JSExportParent {
+            type_id: TypeId::of::<Parent>,
+            parent: parent_of::<Parent>,
+            prototype: prototype_of::<Parent>,
+            upcast: upcast::<Parent, Child>,
+            upcast_mut: upcast_mut::<Parent, Child>,
+        }
+    }
+}
+
+fn parent_of<T: JSExportClass>() -> Option<JSExportParent> {
+    T::PARENT
+}
+
+fn prototype_of<'r, 'c, T: JSExportClass>(
+    in_context: &'c JSContext<'r, 'c>,
+) -> EsperantoResult<Retain<JSValue<'r, 'c>>> {
+    JSValue::prototype_for::<T>(in_context)
+}
+
+fn upcast<Parent: 'static, Child: AsRef<Parent> + 'static>(data: &dyn Any) -> Option<&dyn Any> {
+    data.downcast_ref::<Child>()
+        .map(|child| child.as_ref() as &dyn Any)
+}
+
+fn upcast_mut<Parent: 'static, Child: AsMut<Parent> + 'static>(
+    data: &mut dyn Any,
+) -> Option<&mut dyn Any> {
+    data.downcast_mut::<Child>()
+        .map(|child| child.as_mut() as &mut dyn Any)
+}
+
+/// Whether T is the class with the given type ID or one of its ancestors
+pub(crate) fn is_same_or_ancestor<T: JSExportClass>(
+    type_id: TypeId,
+    mut parent: Option<JSExportParent>,
+) -> bool {
+    if type_id == TypeId::of::<T>() {
+        return true;
+    }
+    while let Some(current) = parent {
+        if (current.type_id)() == TypeId::of::<T>() {
+            return true;
+        }
+        parent = (current.parent)();
+    }
+    false
+}
+
+// Walks up the parent chain from `data` until it finds a T
+pub(crate) fn upcast_to<T: JSExportClass>(
+    mut data: &dyn Any,
+    mut parent: Option<JSExportParent>,
+) -> Option<&T> {
+    loop {
+        if data.is::<T>() {
+            return data.downcast_ref::<T>();
+        }
+        let current = parent?;
+        data = (current.upcast)(data)?;
+        parent = (current.parent)();
+    }
+}
+
+pub(crate) fn upcast_to_mut<T: JSExportClass>(
+    mut data: &mut dyn Any,
+    mut parent: Option<JSExportParent>,
+) -> Option<&mut T> {
+    loop {
+        if data.is::<T>() {
+            return data.downcast_mut::<T>();
+        }
+        let current = parent?;
+        data = (current.upcast_mut)(data)?;
+        parent = (current.parent)();
+    }
 }
 
 pub type JSExportAttributes = Option<phf::OrderedMap<&'static str, JSExportAttribute>>;
 pub type JSExportName = &'static str;
+
+// Engines attach the index of an attribute to the native functions they create for it, then
+// use these to get back to the attribute when the function is called.
+
+pub(crate) fn attributes_for<T: JSExportClass>(is_static: bool) -> JSExportAttributes {
+    match is_static {
+        true => T::STATIC_ATTRIBUTES,
+        false => T::ATTRIBUTES,
+    }
+}
+
+pub(crate) fn attribute_at_index(
+    attributes: &JSExportAttributes,
+    index: usize,
+) -> Option<&JSExportAttribute> {
+    attributes
+        .as_ref()
+        .and_then(|attrs| attrs.index(index))
+        .map(|(_, attribute)| attribute)
+}
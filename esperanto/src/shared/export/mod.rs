@@ -3,6 +3,6 @@ mod jsexportclass;
 mod js_wrapper;
 mod jsexport_private_data;
 
-pub use js_wrapper::Js;
+pub use js_wrapper::{Js, JsMut};
 pub(crate) use jsexport_private_data::JSExportPrivateData;
 pub use jsexportclass::*;
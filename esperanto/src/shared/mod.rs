@@ -2,10 +2,12 @@ pub mod context;
 mod engine_impl;
 pub mod retain;
 pub mod runtime;
+#[cfg(feature = "serde")]
+pub mod serialization;
 // pub mod retainable;
 pub mod as_ptr;
 pub mod errors;
 pub mod export;
 pub mod try_as;
-mod util;
+pub(crate) mod util;
 pub mod value;
@@ -0,0 +1,270 @@
+use serde::de::value::StringDeserializer;
+use serde::de::{
+    self, DeserializeSeed, Error, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::shared::errors::{EsperantoError, EsperantoResult};
+use crate::{JSValue, JSValueType, Retain};
+
+/// A serde Deserializer that reads from an existing JSValue. Accepts the same shapes that
+/// JSValueSerializer produces: null and undefined are both treated as unit or `None`.
+pub struct JSValueDeserializer<'a, 'r, 'c> {
+    value: &'a JSValue<'r, 'c>,
+}
+
+impl<'a, 'r, 'c> JSValueDeserializer<'a, 'r, 'c> {
+    pub fn new(value: &'a JSValue<'r, 'c>) -> Self {
+        JSValueDeserializer { value }
+    }
+}
+
+impl<'de, 'a, 'r: 'c, 'c> de::Deserializer<'de> for JSValueDeserializer<'a, 'r, 'c> {
+    type Error = EsperantoError;
+
+    fn deserialize_any<V>(self, visitor: V) -> EsperantoResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.value;
+
+        match value.value_type() {
+            JSValueType::Undefined | JSValueType::Null => visitor.visit_unit(),
+            JSValueType::Boolean => visitor.visit_bool(value.try_convert()?),
+            JSValueType::Number => {
+                let n: f64 = value.try_convert()?;
+                // Whole numbers are passed on as integers, otherwise integer fields would refuse
+                // to accept them.
+                if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 {
+                    visitor.visit_i64(n as i64)
+                } else {
+                    visitor.visit_f64(n)
+                }
+            }
+            JSValueType::BigInt => match value.try_convert::<i64>() {
+                Ok(n) => visitor.visit_i64(n),
+                Err(_) => visitor.visit_u64(value.try_convert()?),
+            },
+            JSValueType::String => visitor.visit_string(value.try_convert()?),
+            JSValueType::Object if value.is_array()? => visitor.visit_seq(ArrayAccess::new(value)?),
+            JSValueType::Object => visitor.visit_map(ObjectAccess::new(value)?),
+            JSValueType::Symbol => Err(Error::invalid_type(Unexpected::Other("symbol"), &visitor)),
+            JSValueType::Function => {
+                Err(Error::invalid_type(Unexpected::Other("function"), &visitor))
+            }
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> EsperantoResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.value.is_undefined() || self.value.is_null() {
+            return visitor.visit_none();
+        }
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _: &'static str, visitor: V) -> EsperantoResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants are plain strings, everything else is an object with the variant name
+    // as its only key.
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> EsperantoResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.value.is_string() {
+            return visitor.visit_enum(EnumAccess {
+                variant: self.value.try_convert()?,
+                value: None,
+            });
+        }
+
+        if self.value.is_object() {
+            let mut names = self.value.own_property_names()?;
+            if names.len() != 1 {
+                return Err(Error::invalid_length(
+                    names.len(),
+                    &"an object with a single key",
+                ));
+            }
+            let variant = names.remove(0);
+            let value = self.value.get_property(&variant)?;
+            return visitor.visit_enum(EnumAccess {
+                variant,
+                value: Some(value),
+            });
+        }
+
+        Err(Error::invalid_type(
+            Unexpected::Other("JS value"),
+            &"a string or an object with a single key",
+        ))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct ArrayAccess<'a, 'r, 'c> {
+    array: &'a JSValue<'r, 'c>,
+    index: usize,
+    length: usize,
+}
+
+impl<'a, 'r: 'c, 'c> ArrayAccess<'a, 'r, 'c> {
+    fn new(array: &'a JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        Ok(ArrayAccess {
+            array,
+            index: 0,
+            length: array.array_length()?,
+        })
+    }
+}
+
+impl<'de, 'a, 'r: 'c, 'c> SeqAccess<'de> for ArrayAccess<'a, 'r, 'c> {
+    type Error = EsperantoError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> EsperantoResult<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.index >= self.length {
+            return Ok(None);
+        }
+        let item = self.array.get_property_at_index(self.index as u32)?;
+        self.index += 1;
+        seed.deserialize(JSValueDeserializer::new(&item)).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.length - self.index)
+    }
+}
+
+struct ObjectAccess<'a, 'r, 'c> {
+    object: &'a JSValue<'r, 'c>,
+    names: std::vec::IntoIter<String>,
+    // The value for the key we most recently returned
+    pending_value: Option<Retain<JSValue<'r, 'c>>>,
+}
+
+impl<'a, 'r: 'c, 'c> ObjectAccess<'a, 'r, 'c> {
+    fn new(object: &'a JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        Ok(ObjectAccess {
+            object,
+            names: object.own_property_names()?.into_iter(),
+            pending_value: None,
+        })
+    }
+}
+
+impl<'de, 'a, 'r: 'c, 'c> MapAccess<'de> for ObjectAccess<'a, 'r, 'c> {
+    type Error = EsperantoError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> EsperantoResult<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let name = match self.names.next() {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        self.pending_value = Some(self.object.get_property(&name)?);
+        seed.deserialize(name.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> EsperantoResult<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self
+            .pending_value
+            .take()
+            .ok_or_else(|| EsperantoError::custom("map value requested before its key"))?;
+        seed.deserialize(JSValueDeserializer::new(&value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.names.len())
+    }
+}
+
+struct EnumAccess<'r, 'c> {
+    variant: String,
+    // None for unit variants, which are represented by the variant name alone
+    value: Option<Retain<JSValue<'r, 'c>>>,
+}
+
+impl<'de, 'r: 'c, 'c> de::EnumAccess<'de> for EnumAccess<'r, 'c> {
+    type Error = EsperantoError;
+    type Variant = VariantAccess<'r, 'c>;
+
+    fn variant_seed<V>(self, seed: V) -> EsperantoResult<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant: StringDeserializer<EsperantoError> = self.variant.into_deserializer();
+        let variant = seed.deserialize(variant)?;
+        Ok((variant, VariantAccess { value: self.value }))
+    }
+}
+
+struct VariantAccess<'r, 'c> {
+    value: Option<Retain<JSValue<'r, 'c>>>,
+}
+
+impl<'r: 'c, 'c> VariantAccess<'r, 'c> {
+    fn take_value(self, expected: &str) -> EsperantoResult<Retain<JSValue<'r, 'c>>> {
+        self.value
+            .ok_or_else(|| Error::invalid_type(Unexpected::UnitVariant, &expected))
+    }
+}
+
+impl<'de, 'r: 'c, 'c> de::VariantAccess<'de> for VariantAccess<'r, 'c> {
+    type Error = EsperantoError;
+
+    fn unit_variant(self) -> EsperantoResult<()> {
+        match self.value {
+            None => Ok(()),
+            Some(value) => de::Deserialize::deserialize(JSValueDeserializer::new(&value)),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> EsperantoResult<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let value = self.take_value("newtype variant")?;
+        seed.deserialize(JSValueDeserializer::new(&value))
+    }
+
+    fn tuple_variant<V>(self, _: usize, visitor: V) -> EsperantoResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.take_value("tuple variant")?;
+        de::Deserializer::deserialize_seq(JSValueDeserializer::new(&value), visitor)
+    }
+
+    fn struct_variant<V>(self, _: &'static [&'static str], visitor: V) -> EsperantoResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.take_value("struct variant")?;
+        de::Deserializer::deserialize_map(JSValueDeserializer::new(&value), visitor)
+    }
+}
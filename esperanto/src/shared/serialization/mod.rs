@@ -0,0 +1,45 @@
+//! Serde support, enabled with the `serde` feature. Lets you turn any `Serialize` type
+//! straight into a JSValue (and any `Deserialize` type back out of one) without going
+//! through JSON strings.
+
+mod de;
+mod ser;
+
+use std::fmt::Display;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+pub use de::JSValueDeserializer;
+pub use ser::JSValueSerializer;
+
+use crate::shared::errors::{ConversionError, EsperantoError, EsperantoResult};
+use crate::shared::value::ValueResult;
+use crate::{JSContext, JSValue};
+
+/// Serializes a value into a new JSValue in the given context.
+pub fn to_value<'r, 'c, T>(value: &T, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(JSValueSerializer::new(in_context))
+}
+
+/// Deserializes a JSValue into any type that implements `Deserialize`.
+pub fn from_value<T>(value: &JSValue) -> EsperantoResult<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(JSValueDeserializer::new(value))
+}
+
+impl serde::ser::Error for EsperantoError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ConversionError::CouldNotSerialize(msg.to_string()).into()
+    }
+}
+
+impl serde::de::Error for EsperantoError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ConversionError::CouldNotDeserialize(msg.to_string()).into()
+    }
+}
@@ -0,0 +1,395 @@
+use serde::ser::{self, Error, Serialize};
+
+use crate::shared::errors::{EsperantoError, EsperantoResult};
+use crate::shared::value::ValueResult;
+use crate::{JSContext, JSValue, Retain};
+
+/// A serde Serializer that creates values in the given JSContext. Structs and maps become
+/// plain objects, sequences and tuples become arrays, `None` and unit become null and enums
+/// are externally tagged, the same way serde_json represents them.
+#[derive(Clone, Copy)]
+pub struct JSValueSerializer<'r, 'c> {
+    context: &'c JSContext<'r, 'c>,
+}
+
+impl<'r, 'c> JSValueSerializer<'r, 'c> {
+    pub fn new(in_context: &'c JSContext<'r, 'c>) -> Self {
+        JSValueSerializer {
+            context: in_context,
+        }
+    }
+}
+
+// Non-unit enum variants are wrapped in an object with the variant name as the only key
+fn wrap_in_variant<'r, 'c>(
+    variant: &'static str,
+    value: Retain<JSValue<'r, 'c>>,
+    in_context: &'c JSContext<'r, 'c>,
+) -> ValueResult<'r, 'c> {
+    let object = JSValue::new_object(in_context)?;
+    object.set_property(variant, &value)?;
+    Ok(object)
+}
+
+impl<'r: 'c, 'c> ser::Serializer for JSValueSerializer<'r, 'c> {
+    type Ok = Retain<JSValue<'r, 'c>>;
+    type Error = EsperantoError;
+
+    type SerializeSeq = ArraySerializer<'r, 'c>;
+    type SerializeTuple = ArraySerializer<'r, 'c>;
+    type SerializeTupleStruct = ArraySerializer<'r, 'c>;
+    type SerializeTupleVariant = VariantSerializer<ArraySerializer<'r, 'c>>;
+    type SerializeMap = ObjectSerializer<'r, 'c>;
+    type SerializeStruct = ObjectSerializer<'r, 'c>;
+    type SerializeStructVariant = VariantSerializer<ObjectSerializer<'r, 'c>>;
+
+    fn serialize_bool(self, v: bool) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_i8(self, v: i8) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_i16(self, v: i16) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_i32(self, v: i32) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    // 64-bit integers outside the safe integer range become BigInts, same as try_new_from
+    fn serialize_i64(self, v: i64) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_u8(self, v: u8) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_u16(self, v: u16) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_u32(self, v: u32) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_u64(self, v: u64) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_f32(self, v: f32) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_f64(self, v: f64) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_char(self, v: char) -> ValueResult<'r, 'c> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> ValueResult<'r, 'c> {
+        JSValue::try_new_from(v, self.context)
+    }
+
+    fn serialize_none(self) -> ValueResult<'r, 'c> {
+        Ok(JSValue::null(self.context))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> ValueResult<'r, 'c>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> ValueResult<'r, 'c> {
+        Ok(JSValue::null(self.context))
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> ValueResult<'r, 'c> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> ValueResult<'r, 'c> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> ValueResult<'r, 'c>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> ValueResult<'r, 'c>
+    where
+        T: Serialize + ?Sized,
+    {
+        wrap_in_variant(variant, value.serialize(self)?, self.context)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> EsperantoResult<Self::SerializeSeq> {
+        Ok(ArraySerializer::new(self, len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> EsperantoResult<Self::SerializeTuple> {
+        Ok(ArraySerializer::new(self, Some(len)))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> EsperantoResult<Self::SerializeTupleStruct> {
+        Ok(ArraySerializer::new(self, Some(len)))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> EsperantoResult<Self::SerializeTupleVariant> {
+        Ok(VariantSerializer {
+            variant,
+            inner: ArraySerializer::new(self, Some(len)),
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> EsperantoResult<Self::SerializeMap> {
+        ObjectSerializer::new(self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> EsperantoResult<Self::SerializeStruct> {
+        ObjectSerializer::new(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> EsperantoResult<Self::SerializeStructVariant> {
+        Ok(VariantSerializer {
+            variant,
+            inner: ObjectSerializer::new(self)?,
+        })
+    }
+}
+
+/// Holds on to each serialized item until we have all of them and can create the array.
+pub struct ArraySerializer<'r, 'c> {
+    serializer: JSValueSerializer<'r, 'c>,
+    items: Vec<Retain<JSValue<'r, 'c>>>,
+}
+
+impl<'r: 'c, 'c> ArraySerializer<'r, 'c> {
+    fn new(serializer: JSValueSerializer<'r, 'c>, len: Option<usize>) -> Self {
+        ArraySerializer {
+            serializer,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        }
+    }
+
+    fn push<T>(&mut self, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.items.push(value.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn create_array(self) -> ValueResult<'r, 'c> {
+        let items = self.items.iter().map(|item| &**item).collect();
+        JSValue::new_array(items, self.serializer.context)
+    }
+}
+
+impl<'r: 'c, 'c> ser::SerializeSeq for ArraySerializer<'r, 'c> {
+    type Ok = Retain<JSValue<'r, 'c>>;
+    type Error = EsperantoError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> ValueResult<'r, 'c> {
+        self.create_array()
+    }
+}
+
+impl<'r: 'c, 'c> ser::SerializeTuple for ArraySerializer<'r, 'c> {
+    type Ok = Retain<JSValue<'r, 'c>>;
+    type Error = EsperantoError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> ValueResult<'r, 'c> {
+        self.create_array()
+    }
+}
+
+impl<'r: 'c, 'c> ser::SerializeTupleStruct for ArraySerializer<'r, 'c> {
+    type Ok = Retain<JSValue<'r, 'c>>;
+    type Error = EsperantoError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> ValueResult<'r, 'c> {
+        self.create_array()
+    }
+}
+
+/// Sets each field on the object as it's serialized.
+pub struct ObjectSerializer<'r, 'c> {
+    serializer: JSValueSerializer<'r, 'c>,
+    object: Retain<JSValue<'r, 'c>>,
+    // Map keys and values are serialized separately, so we hold on to the key in between
+    pending_key: Option<String>,
+}
+
+impl<'r: 'c, 'c> ObjectSerializer<'r, 'c> {
+    fn new(serializer: JSValueSerializer<'r, 'c>) -> EsperantoResult<Self> {
+        Ok(ObjectSerializer {
+            serializer,
+            object: JSValue::new_object(serializer.context)?,
+            pending_key: None,
+        })
+    }
+
+    fn set_field<T>(&mut self, key: &str, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let value = value.serialize(self.serializer)?;
+        self.object.set_property(key, &value)
+    }
+}
+
+impl<'r: 'c, 'c> ser::SerializeMap for ObjectSerializer<'r, 'c> {
+    type Ok = Retain<JSValue<'r, 'c>>;
+    type Error = EsperantoError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        // JS objects can only have string keys, but numbers get stringified for us
+        // when used as keys, so we do the same here.
+        let key = key.serialize(self.serializer)?;
+        if key.is_string() == false && key.is_number() == false {
+            return Err(EsperantoError::custom(
+                "map keys must be strings or numbers",
+            ));
+        }
+        self.pending_key = Some(key.try_convert()?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .pending_key
+            .take()
+            .ok_or_else(|| EsperantoError::custom("map value serialized before its key"))?;
+        self.set_field(&key, value)
+    }
+
+    fn end(self) -> ValueResult<'r, 'c> {
+        Ok(self.object)
+    }
+}
+
+impl<'r: 'c, 'c> ser::SerializeStruct for ObjectSerializer<'r, 'c> {
+    type Ok = Retain<JSValue<'r, 'c>>;
+    type Error = EsperantoError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.set_field(key, value)
+    }
+
+    fn end(self) -> ValueResult<'r, 'c> {
+        Ok(self.object)
+    }
+}
+
+/// Serializes the contents of a tuple or struct variant, then wraps it in an object keyed
+/// by the variant name.
+pub struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<'r: 'c, 'c> ser::SerializeTupleVariant for VariantSerializer<ArraySerializer<'r, 'c>> {
+    type Ok = Retain<JSValue<'r, 'c>>;
+    type Error = EsperantoError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner.push(value)
+    }
+
+    fn end(self) -> ValueResult<'r, 'c> {
+        let context = self.inner.serializer.context;
+        wrap_in_variant(self.variant, self.inner.create_array()?, context)
+    }
+}
+
+impl<'r: 'c, 'c> ser::SerializeStructVariant for VariantSerializer<ObjectSerializer<'r, 'c>> {
+    type Ok = Retain<JSValue<'r, 'c>>;
+    type Error = EsperantoError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> EsperantoResult<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner.set_field(key, value)
+    }
+
+    fn end(self) -> ValueResult<'r, 'c> {
+        let context = self.inner.serializer.context;
+        wrap_in_variant(self.variant, self.inner.object, context)
+    }
+}
@@ -1,3 +1,7 @@
+mod raw_args;
 mod stored_or_referenced;
+mod wtf8;
 
+pub(crate) use raw_args::*;
 pub(crate) use stored_or_referenced::*;
+pub(crate) use wtf8::*;
@@ -0,0 +1,8 @@
+/// Engines pass a null pointer rather than an empty array when a function is called with no
+/// arguments, which slice::from_raw_parts doesn't allow. This turns either into an empty slice.
+pub(crate) unsafe fn arguments_from_raw_parts<'a, T>(argv: *const T, argc: usize) -> &'a [T] {
+    if argc == 0 || argv.is_null() {
+        return &[];
+    }
+    std::slice::from_raw_parts(argv, argc)
+}
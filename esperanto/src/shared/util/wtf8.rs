@@ -0,0 +1,89 @@
+// JS strings are sequences of UTF-16 code units and are allowed to contain unpaired
+// surrogates, which neither a Rust String nor strict UTF-8 can represent. WTF-8 is UTF-8
+// extended to encode those surrogates as ordinary three byte sequences. QuickJS reads and
+// writes strings in that form while JavaScriptCore uses UTF-16, so these functions convert
+// between the two.
+
+/// Encodes UTF-16 code units as WTF-8. Valid UTF-16 produces exactly the same bytes as UTF-8.
+pub(crate) fn wtf8_from_utf16(units: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(units.len());
+    for decoded in std::char::decode_utf16(units.iter().copied()) {
+        match decoded {
+            Ok(char) => {
+                let mut buffer = [0; 4];
+                bytes.extend_from_slice(char.encode_utf8(&mut buffer).as_bytes());
+            }
+            Err(error) => {
+                let surrogate = error.unpaired_surrogate();
+                bytes.push(0xE0 | (surrogate >> 12) as u8);
+                bytes.push(0x80 | ((surrogate >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (surrogate & 0x3F) as u8);
+            }
+        }
+    }
+    bytes
+}
+
+/// Decodes WTF-8 (which includes any valid UTF-8) to UTF-16 code units. The bytes are expected
+/// to have come from a JS engine, so malformed sequences aren't checked for.
+pub(crate) fn utf16_from_wtf8(bytes: &[u8]) -> Vec<u16> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let first = bytes[index] as u32;
+        let (length, initial_bits) = match first {
+            0x00..=0x7F => (1, first),
+            0xC0..=0xDF => (2, first & 0x1F),
+            0xE0..=0xEF => (3, first & 0x0F),
+            _ => (4, first & 0x07),
+        };
+
+        let code_point = bytes
+            .iter()
+            .skip(index + 1)
+            .take(length - 1)
+            .fold(initial_bits, |code_point, byte| {
+                (code_point << 6) | (*byte as u32 & 0x3F)
+            });
+        index += length;
+
+        if code_point >= 0x10000 {
+            let offset = code_point - 0x10000;
+            units.push(0xD800 + (offset >> 10) as u16);
+            units.push(0xDC00 + (offset & 0x3FF) as u16);
+        } else {
+            units.push(code_point as u16);
+        }
+    }
+    units
+}
+
+/// Converts WTF-8 to a String, replacing any unpaired surrogates with U+FFFD.
+pub(crate) fn string_from_wtf8_lossy(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(str) => str.to_owned(),
+        Err(_) => String::from_utf16_lossy(&utf16_from_wtf8(bytes)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{string_from_wtf8_lossy, utf16_from_wtf8, wtf8_from_utf16};
+
+    #[test]
+    fn round_trips_valid_utf16() {
+        let units: Vec<u16> = "a\0é€😀".encode_utf16().collect();
+        let bytes = wtf8_from_utf16(&units);
+        assert_eq!(bytes, "a\0é€😀".as_bytes());
+        assert_eq!(utf16_from_wtf8(&bytes), units);
+    }
+
+    #[test]
+    fn round_trips_unpaired_surrogates() {
+        let units = vec![0x61, 0xD83D, 0x62, 0xDE00];
+        let bytes = wtf8_from_utf16(&units);
+        assert_eq!(bytes, vec![0x61, 0xED, 0xA0, 0xBD, 0x62, 0xED, 0xB8, 0x80]);
+        assert_eq!(utf16_from_wtf8(&bytes), units);
+        assert_eq!(string_from_wtf8_lossy(&bytes), "a\u{FFFD}b\u{FFFD}");
+    }
+}
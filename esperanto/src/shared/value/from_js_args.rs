@@ -0,0 +1,117 @@
+use super::TryConvertJSValue;
+use crate::shared::errors::{EsperantoResult, JavaScriptError};
+use crate::{JSContext, JSValue};
+
+/// Converts the arguments a native function was called with into a tuple of Rust types,
+/// so that callbacks don't need to bounds check and convert each argument by hand:
+///
+/// ```ignore
+/// func: |args, ctx| {
+///     let (name, count): (String, Option<f64>) = FromJSArgs::from_js_args(args, ctx)?;
+///     ...
+/// }
+/// ```
+///
+/// Missing arguments are treated as undefined, same as they would be in a JS function, which
+/// means trailing `Option<T>` arguments can be left off entirely. Arguments are coerced into
+/// the type you ask for unless it's wrapped in `Strict<T>`. Any argument that fails to
+/// convert is thrown back to JS as a `TypeError` naming the argument index and expected type.
+pub trait FromJSArgs<'r: 'c, 'c>: Sized {
+    fn from_js_args(
+        args: &[&JSValue<'r, 'c>],
+        in_context: &'c JSContext<'r, 'c>,
+    ) -> EsperantoResult<Self>;
+}
+
+fn convert_argument<'r: 'c, 'c, T>(
+    args: &[&JSValue<'r, 'c>],
+    index: usize,
+    undefined: &JSValue<'r, 'c>,
+) -> EsperantoResult<T>
+where
+    T: TryConvertJSValue<'r, 'c>,
+{
+    let value = args.get(index).copied().unwrap_or(undefined);
+    value.try_convert().map_err(|err| {
+        let message = format!(
+            "Argument {} could not be converted to {}: {}",
+            index,
+            short_type_name(std::any::type_name::<T>()),
+            err
+        );
+        JavaScriptError::new("TypeError".to_string(), message).into()
+    })
+}
+
+// type_name gives us fully qualified paths like core::option::Option<alloc::string::String>,
+// which is a lot of noise in an error message. We only keep the last segment of each path.
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            short.truncate(segment_start);
+            continue;
+        }
+        short.push(c);
+        if c.is_alphanumeric() == false && c != '_' {
+            segment_start = short.len();
+        }
+    }
+
+    short
+}
+
+impl<'r: 'c, 'c> FromJSArgs<'r, 'c> for () {
+    fn from_js_args(_: &[&JSValue<'r, 'c>], _: &'c JSContext<'r, 'c>) -> EsperantoResult<Self> {
+        Ok(())
+    }
+}
+
+macro_rules! tuple_from_js_args {
+    ($($type:ident => $index:tt),+) => {
+        impl<'r: 'c, 'c, $($type),+> FromJSArgs<'r, 'c> for ($($type,)+)
+        where
+            $($type: TryConvertJSValue<'r, 'c>),+
+        {
+            fn from_js_args(
+                args: &[&JSValue<'r, 'c>],
+                in_context: &'c JSContext<'r, 'c>,
+            ) -> EsperantoResult<Self> {
+                let undefined = JSValue::undefined(in_context);
+                Ok(($(convert_argument::<$type>(args, $index, &undefined)?,)+))
+            }
+        }
+    };
+}
+
+tuple_from_js_args!(A => 0);
+tuple_from_js_args!(A => 0, B => 1);
+tuple_from_js_args!(A => 0, B => 1, C => 2);
+tuple_from_js_args!(A => 0, B => 1, C => 2, D => 3);
+tuple_from_js_args!(A => 0, B => 1, C => 2, D => 3, E => 4);
+tuple_from_js_args!(A => 0, B => 1, C => 2, D => 3, E => 4, F => 5);
+tuple_from_js_args!(A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6);
+tuple_from_js_args!(A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6, H => 7);
+
+#[cfg(test)]
+mod test {
+    use super::short_type_name;
+
+    #[test]
+    fn shortens_type_names() {
+        assert_eq!(short_type_name("f64"), "f64");
+        assert_eq!(short_type_name("alloc::string::String"), "String");
+        assert_eq!(
+            short_type_name("core::option::Option<alloc::string::String>"),
+            "Option<String>"
+        );
+        assert_eq!(
+            short_type_name("esperanto::shared::errors::JavaScriptError"),
+            "JavaScriptError"
+        );
+    }
+}
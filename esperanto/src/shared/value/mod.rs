@@ -1,15 +1,25 @@
 mod as_value;
+mod from_js_args;
 mod has_value;
+mod property_key;
 mod value;
 mod value_conversion;
 mod value_error;
 mod value_implementation;
+mod value_iterator;
+mod value_type;
+mod well_known_symbol;
 
 pub use as_value::AsJSValueRef;
+pub use from_js_args::FromJSArgs;
 // pub use result as JSResult;
 pub use has_value::HasJSValue;
+pub use property_key::PropertyKey;
 pub use value::JSValue;
 pub(crate) use value::ValueResult;
-pub use value_conversion::{JSValueFrom, TryConvertJSValue, TryJSValueFrom};
+pub use value_conversion::{JSValueFrom, Nullable, Strict, TryConvertJSValue, TryJSValueFrom};
 pub use value_error::JSValueError;
-pub(crate) use value_implementation::JSValueImplementation;
+pub(crate) use value_implementation::{ErrorLocation, JSValueImplementation, NativeFunction};
+pub use value_iterator::JSValueIterator;
+pub use value_type::JSValueType;
+pub use well_known_symbol::WellKnownSymbol;
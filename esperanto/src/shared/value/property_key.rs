@@ -0,0 +1,46 @@
+use crate::{JSValue, Retain};
+
+/// The key used to get, set or delete a property on an object. You usually won't need to
+/// create one directly: the property methods on JSValue accept anything that converts into
+/// a key, i.e. strings, u32 indexes and JSValues.
+#[derive(Debug, Clone, Copy)]
+pub enum PropertyKey<'a, 'r, 'c> {
+    /// A string-keyed property. Unlike the engines' C string APIs the name can contain NUL.
+    Name(&'a str),
+    /// An integer-keyed property, e.g. an array element. These are looked up directly rather
+    /// than being turned into a string first.
+    Index(u32),
+    /// A symbol-keyed property. Any other value is converted into a key the same way
+    /// `object[value]` would in JS.
+    Symbol(&'a JSValue<'r, 'c>),
+}
+
+impl<'a> From<&'a str> for PropertyKey<'a, '_, '_> {
+    fn from(name: &'a str) -> Self {
+        PropertyKey::Name(name)
+    }
+}
+
+impl<'a> From<&'a String> for PropertyKey<'a, '_, '_> {
+    fn from(name: &'a String) -> Self {
+        PropertyKey::Name(name.as_str())
+    }
+}
+
+impl From<u32> for PropertyKey<'_, '_, '_> {
+    fn from(index: u32) -> Self {
+        PropertyKey::Index(index)
+    }
+}
+
+impl<'a, 'r, 'c> From<&'a JSValue<'r, 'c>> for PropertyKey<'a, 'r, 'c> {
+    fn from(value: &'a JSValue<'r, 'c>) -> Self {
+        PropertyKey::Symbol(value)
+    }
+}
+
+impl<'a, 'r, 'c> From<&'a Retain<JSValue<'r, 'c>>> for PropertyKey<'a, 'r, 'c> {
+    fn from(value: &'a Retain<JSValue<'r, 'c>>) -> Self {
+        PropertyKey::Symbol(value)
+    }
+}
@@ -1,17 +1,21 @@
-use std::{ffi::CString, fmt::Display};
+use std::{cell::RefCell, ffi::CString, fmt::Display};
 
 use crate::{
-    export::Js,
+    export::{Js, JsMut},
     shared::{
-        context::JSContext,
-        errors::{ConversionError, EsperantoResult},
+        context::{get_intrinsic, Intrinsic, JSContext},
+        errors::{ConversionError, EsperantoResult, JSExportError},
+        util::string_from_wtf8_lossy,
     },
     JSExportClass, Retain, TryJSValueFrom,
 };
 
 use crate::shared::engine_impl::JSValueInternalImpl;
 
-use super::{value_implementation::JSValueImplementation, TryConvertJSValue};
+use super::{
+    value_implementation::JSValueImplementation, JSValueError, JSValueIterator, JSValueType,
+    PropertyKey, TryConvertJSValue, WellKnownSymbol,
+};
 
 #[derive(Debug, Eq)]
 pub struct JSValue<'r, 'c> {
@@ -30,11 +34,8 @@ pub(crate) type ValueResult<'r, 'c> = EsperantoResult<Retain<JSValue<'r, 'c>>>;
 
 impl Display for JSValue<'_, '_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.internal.as_cstring(self.context.implementation()) {
-            Ok(cstring) => {
-                let str = cstring.to_string_lossy();
-                write!(f, "{}", str)
-            }
+        match self.to_string_lossy() {
+            Ok(str) => write!(f, "{}", str),
             Err(err) => {
                 write!(f, "[could not represent JSValue: {}]", err)
             }
@@ -46,24 +47,28 @@ impl<'r, 'c> JSValue<'r, 'c>
 where
     'r: 'c,
 {
-    pub fn set_property(&self, name: &str, value: &Self) -> EsperantoResult<()> {
-        let name_cstring =
-            CString::new(name).map_err(|e| ConversionError::CouldNotConvertToJSString(e))?;
-
-        JSValueInternalImpl::set_property(
-            self.internal,
+    /// Sets a property on the object. The key can be a string, a u32 index or a symbol, see
+    /// `PropertyKey` for details.
+    pub fn set_property<'a, K>(&self, key: K, value: &Self) -> EsperantoResult<()>
+    where
+        K: Into<PropertyKey<'a, 'r, 'c>>,
+        'c: 'a,
+    {
+        self.internal.set_property_for_key(
             self.context.implementation(),
-            &name_cstring,
+            key.into(),
             value.internal,
         )
     }
-    pub fn get_property(&self, name: &str) -> ValueResult<'r, 'c> {
-        let name_cstring =
-            CString::new(name).map_err(|e| ConversionError::CouldNotConvertToJSString(e))?;
 
+    pub fn get_property<'a, K>(&self, key: K) -> ValueResult<'r, 'c>
+    where
+        K: Into<PropertyKey<'a, 'r, 'c>>,
+        'c: 'a,
+    {
         let val = self
             .internal
-            .get_property(self.context.implementation(), &name_cstring)
+            .get_property_for_key(self.context.implementation(), key.into())
             .map(|p| JSValue {
                 internal: p,
                 context: self.context,
@@ -72,12 +77,84 @@ where
         return Ok(Retain::wrap(val));
     }
 
-    pub fn delete_property(&self, name: &str) -> EsperantoResult<bool> {
-        let name_cstring =
-            CString::new(name).map_err(|e| ConversionError::CouldNotConvertToJSString(e))?;
+    pub fn delete_property<'a, K>(&self, key: K) -> EsperantoResult<bool>
+    where
+        K: Into<PropertyKey<'a, 'r, 'c>>,
+        'c: 'a,
+    {
+        self.internal
+            .delete_property_for_key(self.context.implementation(), key.into())
+    }
+
+    /// The names of this object's own enumerable string-keyed properties, i.e. what you'd get
+    /// from `Object.keys()`. Same as converting a string, names containing unpaired surrogates
+    /// can't be represented and return an error.
+    pub fn own_property_names(&self) -> EsperantoResult<Vec<String>> {
+        let names = self
+            .internal
+            .own_property_names(self.context.implementation())?;
+
+        names
+            .into_iter()
+            .map(|name| {
+                String::from_utf8(name).map_err(|e| {
+                    ConversionError::CouldNotConvertFromJSString(e.utf8_error()).into()
+                })
+            })
+            .collect()
+    }
+
+    /// Gets an array element (or any other integer-keyed property) without having to turn
+    /// the index into a string first.
+    pub fn get_property_at_index(&self, index: u32) -> ValueResult<'r, 'c> {
+        let val = self
+            .internal
+            .get_property_at_index(self.context.implementation(), index)
+            .map(|p| JSValue {
+                internal: p,
+                context: self.context,
+            })?;
+
+        return Ok(Retain::wrap(val));
+    }
 
+    pub fn set_property_at_index(&self, index: u32, value: &Self) -> EsperantoResult<()> {
         self.internal
-            .delete_property(self.context.implementation(), &name_cstring)
+            .set_property_at_index(self.context.implementation(), index, value.internal)
+    }
+
+    /// Serializes the value the same way `JSON.stringify(value, null, indent)` would. Returns
+    /// `None` if the value has no JSON representation (e.g. undefined or a function) and a
+    /// TypeError if it contains a cycle.
+    pub fn to_json_string(&self, indent: u32) -> EsperantoResult<Option<String>> {
+        let json = self
+            .internal
+            .to_json_string(indent, self.context.implementation())?;
+
+        json.map(|cstring| {
+            cstring
+                .into_string()
+                .map_err(|e| ConversionError::CouldNotConvertFromJSString(e.utf8_error()).into())
+        })
+        .transpose()
+    }
+
+    /// Converts the value to a string (the same way `String(value)` would) and returns its
+    /// UTF-16 code units exactly as JS sees them, unpaired surrogates included.
+    pub fn to_utf16(&self) -> EsperantoResult<Vec<u16>> {
+        self.internal.as_utf16(self.context.implementation())
+    }
+
+    /// Like `to_utf16`, but encoded as WTF-8: identical to UTF-8 unless the string contains
+    /// unpaired surrogates, which get the three byte encoding UTF-8 would otherwise reject.
+    pub fn to_wtf8(&self) -> EsperantoResult<Vec<u8>> {
+        self.internal.as_wtf8(self.context.implementation())
+    }
+
+    /// Converts the value to a string, replacing any unpaired surrogates with U+FFFD rather
+    /// than failing the way converting to String does.
+    pub fn to_string_lossy(&self) -> EsperantoResult<String> {
+        Ok(string_from_wtf8_lossy(&self.to_wtf8()?))
     }
 
     pub(crate) fn wrap_internal(
@@ -134,6 +211,13 @@ where
         Js::new(retained)
     }
 
+    /// Like as_native but allows mutation of the native object. Returns an error if the object
+    /// is already borrowed, e.g. by a method further up the stack that has called back into JS.
+    pub fn as_native_mut<T: JSExportClass>(&self) -> EsperantoResult<JsMut<'r, 'c, T>> {
+        let retained = self.retain();
+        JsMut::new(retained)
+    }
+
     pub fn new_function(
         body: &str,
         arguments: Vec<&str>,
@@ -156,11 +240,31 @@ where
         Ok(Retain::wrap(Self::wrap_internal(raw, in_context)))
     }
 
+    /// Creates a JS function that runs the provided closure when called. The closure receives
+    /// the `this` value and arguments the function was called with. Anything it captures is
+    /// dropped when the function is garbage collected.
+    pub fn new_native_function<F>(func: F, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c>
+    where
+        F: for<'r2, 'c2, 'v> Fn(
+                &'c2 JSContext<'r2, 'c2>,
+                &'v JSValue<'r2, 'c2>,
+                &'v [&'v JSValue<'r2, 'c2>],
+            ) -> EsperantoResult<Retain<JSValue<'r2, 'c2>>>
+            + 'static,
+    {
+        let raw = JSValueInternalImpl::new_native_function(
+            Box::new(func),
+            in_context.implementation(),
+            in_context.get_runtime().implementation(),
+        )?;
+        Ok(Retain::wrap(Self::wrap_internal(raw, in_context)))
+    }
+
     pub fn new_error(
         name: &str,
         message: &str,
         in_context: &'c JSContext<'r, 'c>,
-    ) -> EsperantoResult<Self> {
+    ) -> ValueResult<'r, 'c> {
         let name_cstring = CString::new(name)?;
         let message_cstring = CString::new(message)?;
 
@@ -170,7 +274,81 @@ where
             in_context.implementation(),
         );
 
-        Ok(Self::wrap_internal(created, in_context))
+        Ok(Retain::wrap(Self::wrap_internal(created, in_context)))
+    }
+
+    /// Creates an empty object, same as `{}` would in JS.
+    pub fn new_object(in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+        let raw = JSValueInternalImpl::new_object(in_context.implementation())?;
+        Ok(Retain::wrap(Self::wrap_internal(raw, in_context)))
+    }
+
+    /// Iterates over the value the same way `for...of` would, e.g. the items of an array, the
+    /// entries of a Map or the values yielded by a generator. Fails with
+    /// `JSValueError::IsNotIterable` if the value has no `[Symbol.iterator]()` method.
+    pub fn iter(&self) -> EsperantoResult<JSValueIterator<'r, 'c>> {
+        let iterator_symbol = Self::well_known_symbol(WellKnownSymbol::Iterator, self.context)?;
+        JSValueIterator::new(self, &iterator_symbol)
+    }
+
+    /// Wraps a Rust iterator in a JS iterator object, i.e. one with a `next()` method that is
+    /// also iterable itself, so it can be used with `for...of`, spread syntax and so on. Items
+    /// are only taken from the iterator (and converted) when JS asks for them, and the iterator
+    /// is dropped when the object is garbage collected.
+    pub fn new_iterator<I>(iterator: I, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c>
+    where
+        I: Iterator + 'static,
+        I::Item: for<'r2, 'c2> TryJSValueFrom<'r2, 'c2>,
+    {
+        let iterator = RefCell::new(iterator);
+        let next = Self::new_native_function(
+            move |ctx, _, _| {
+                // The borrow ends here so converting the item can't clash with it, but the
+                // iterator itself could still call back into JS and end up in here again
+                let item = iterator
+                    .try_borrow_mut()
+                    .map_err(|_| JSExportError::NativeObjectAlreadyBorrowed("Iterator"))?
+                    .next();
+
+                let result = JSValue::new_object(ctx)?;
+                let done = JSValue::try_new_from(item.is_none(), ctx)?;
+                let value = match item {
+                    Some(item) => JSValue::try_new_from(item, ctx)?,
+                    None => JSValue::undefined(ctx),
+                };
+                result.set_property("value", &value)?;
+                result.set_property("done", &done)?;
+                Ok(result)
+            },
+            in_context,
+        )?;
+
+        let js_iterator = Self::new_object(in_context)?;
+        js_iterator.set_property("next", &next)?;
+
+        // for...of and spread syntax look for [Symbol.iterator]() rather than next()
+        let return_this = Self::new_native_function(|_, this, _| Ok(this.retain()), in_context)?;
+        let iterator_symbol = Self::well_known_symbol(WellKnownSymbol::Iterator, in_context)?;
+        js_iterator.set_property(&iterator_symbol, &return_this)?;
+
+        Ok(js_iterator)
+    }
+
+    pub fn new_array(values: Vec<&Self>, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+        let internal_vec = values.iter().map(|v| v.internal).collect();
+        let raw = JSValueInternalImpl::new_array(internal_vec, in_context.implementation())?;
+        Ok(Retain::wrap(Self::wrap_internal(raw, in_context)))
+    }
+
+    pub fn is_array(&self) -> EsperantoResult<bool> {
+        self.internal.is_array(self.context.implementation())
+    }
+
+    pub fn array_length(&self) -> EsperantoResult<usize> {
+        if self.is_array()? == false {
+            return Err(JSValueError::IsNotAnArray.into());
+        }
+        self.get_property("length")?.try_convert()
     }
 
     pub fn call_as_function(&self, arguments: Vec<&Self>) -> ValueResult<'r, 'c> {
@@ -218,19 +396,106 @@ where
         ))
     }
 
+    pub fn null(in_context: &'c JSContext<'r, 'c>) -> Retain<Self> {
+        Retain::wrap(Self::wrap_internal(
+            JSValueInternalImpl::null(in_context.implementation()),
+            in_context,
+        ))
+    }
+
+    /// Creates a new, unique symbol, same as `Symbol(description)` would in JS.
+    pub fn new_symbol(
+        description: Option<&str>,
+        in_context: &'c JSContext<'r, 'c>,
+    ) -> ValueResult<'r, 'c> {
+        let symbol = JSValueInternalImpl::new_symbol(description, in_context.implementation())?;
+        Ok(Retain::wrap(Self::wrap_internal(symbol, in_context)))
+    }
+
+    /// Gets one of the built-in symbols like `Symbol.iterator`, e.g. to use as a property key.
+    /// These are the original symbols even if a script has since replaced `globalThis.Symbol`.
+    pub fn well_known_symbol(
+        symbol: WellKnownSymbol,
+        in_context: &'c JSContext<'r, 'c>,
+    ) -> ValueResult<'r, 'c> {
+        let ctx = in_context.implementation();
+        let raw = get_intrinsic(ctx, Intrinsic::WellKnownSymbol(symbol))?;
+        Ok(Retain::wrap(Self::wrap_internal(raw.retain(ctx), in_context)))
+    }
+
     pub fn is_instance_of(&self, other: &Self) -> EsperantoResult<bool> {
         self.internal
             .is_instanceof(other.internal, self.context.implementation())
     }
 
+    /// Which of the JS types this value is. Arrays are objects, same as they are in JS, so
+    /// use `is_array()` if you need to tell them apart.
+    pub fn value_type(&self) -> JSValueType {
+        if self.is_undefined() {
+            JSValueType::Undefined
+        } else if self.is_null() {
+            JSValueType::Null
+        } else if self.is_bool() {
+            JSValueType::Boolean
+        } else if self.is_number() {
+            JSValueType::Number
+        } else if self.is_string() {
+            JSValueType::String
+        } else if self.is_symbol() {
+            JSValueType::Symbol
+        } else if self.is_function() {
+            JSValueType::Function
+        } else if self.is_object() {
+            JSValueType::Object
+        } else {
+            JSValueType::BigInt
+        }
+    }
+
     pub fn is_string(&self) -> bool {
         self.internal.is_string(self.context.implementation())
     }
 
+    pub fn is_undefined(&self) -> bool {
+        self.internal.is_undefined(self.context.implementation())
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.internal.is_null(self.context.implementation())
+    }
+
+    pub fn is_bool(&self) -> bool {
+        self.internal.is_bool(self.context.implementation())
+    }
+
+    pub fn is_number(&self) -> bool {
+        self.internal.is_number(self.context.implementation())
+    }
+
+    pub fn is_bigint(&self) -> bool {
+        self.internal.is_bigint(self.context.implementation())
+    }
+
+    pub fn is_symbol(&self) -> bool {
+        self.internal.is_symbol(self.context.implementation())
+    }
+
+    /// Functions are objects too, so this being true means `is_object()` is as well.
+    pub fn is_function(&self) -> bool {
+        self.internal.is_function(self.context.implementation())
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.internal.is_object(self.context.implementation())
+    }
+
     pub fn is_error(&self) -> EsperantoResult<bool> {
         self.internal.is_error(self.context.implementation())
     }
 
+    /// Converts the value into a native type, coercing it the same way JS would if it isn't
+    /// the type we're looking for: objects become "[object Object]", strings are parsed into
+    /// numbers, anything can be truthy, etc.
     pub fn try_convert<'a, T>(&self) -> EsperantoResult<T>
     where
         T: TryConvertJSValue<'r, 'c>,
@@ -239,6 +504,15 @@ where
         T::try_from_jsvalue(&self)
     }
 
+    /// Like `try_convert`, except values of the wrong type are rejected with
+    /// `ConversionError::UnexpectedType` rather than being coerced.
+    pub fn try_convert_strict<T>(&self) -> EsperantoResult<T>
+    where
+        T: TryConvertJSValue<'r, 'c>,
+    {
+        T::try_from_jsvalue_strict(&self)
+    }
+
     pub fn try_new_from<T>(value: T, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c>
     where
         T: TryJSValueFrom<'r, 'c>,
@@ -246,6 +520,16 @@ where
         return T::try_jsvalue_from(value, in_context);
     }
 
+    /// Creates a string from UTF-16 code units. Unlike converting from a Rust string these
+    /// don't have to be valid UTF-16, so unpaired surrogates make it through intact.
+    pub fn try_new_from_utf16(
+        value: &[u16],
+        in_context: &'c JSContext<'r, 'c>,
+    ) -> ValueResult<'r, 'c> {
+        let internal = JSValueInternalImpl::from_utf16(value, in_context.implementation())?;
+        Ok(Retain::wrap(Self::wrap_internal(internal, in_context)))
+    }
+
     pub fn retain(&self) -> Retain<Self> {
         let new_retained = self.internal.retain(self.context.implementation());
         return Retain::wrap(Self::wrap_internal(new_retained, self.context));
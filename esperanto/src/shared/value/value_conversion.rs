@@ -1,7 +1,10 @@
-use std::ffi::CString;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::hash::BuildHasher;
+use std::iter::FromIterator;
 
 use super::value::ValueResult;
-use super::JSValueImplementation;
+use super::{JSValueImplementation, JSValueType};
 use crate::shared::errors::JavaScriptError;
 use crate::shared::{engine_impl::JSValueInternalImpl, errors::EsperantoError};
 use crate::{
@@ -9,19 +12,25 @@ use crate::{
     JSContext, JSValue, Retain,
 };
 
-pub trait TryJSValueFrom<'r, 'c>: Sized
-where
-    'r: 'c,
-{
+// These deliberately don't declare 'r: 'c (taking &'c JSContext<'r, 'c> already implies it)
+// so that types can implement them for any pair of lifetimes. That's what allows a
+// `T: for<'r, 'c> TryJSValueFrom<'r, 'c>` bound on values converted inside native functions.
+pub trait TryJSValueFrom<'r, 'c>: Sized {
     fn try_jsvalue_from(value: Self, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c>;
 }
 
-pub trait JSValueFrom<'r: 'c, 'c>: Sized {
+pub trait JSValueFrom<'r, 'c>: Sized {
     fn jsvalue_from(value: Self, in_context: &'c JSContext<'r, 'c>) -> Retain<JSValue<'r, 'c>>;
 }
 
 pub trait TryConvertJSValue<'r: 'c, 'c>: Sized {
     fn try_from_jsvalue(value: &JSValue<'r, 'c>) -> EsperantoResult<Self>;
+
+    /// The same conversion, but failing with `ConversionError::UnexpectedType` instead of
+    /// coercing a value of the wrong type. Types that never coerce can leave this as is.
+    fn try_from_jsvalue_strict(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        Self::try_from_jsvalue(value)
+    }
 }
 
 // Rather than repeat all these lifetime requirements a million times over
@@ -29,10 +38,7 @@ pub trait TryConvertJSValue<'r: 'c, 'c>: Sized {
 
 macro_rules! try_to_js_value {
     ($target_type:ty, ($value: ident, $in_context:ident) => $body:expr) => {
-        impl<'r, 'c> TryJSValueFrom<'r, 'c> for $target_type
-        where
-            'r: 'c,
-        {
+        impl<'r, 'c> TryJSValueFrom<'r, 'c> for $target_type {
             fn try_jsvalue_from(
                 $value: $target_type,
                 $in_context: &'c JSContext<'r, 'c>,
@@ -51,12 +57,34 @@ macro_rules! try_from_js_value {
             }
         }
     };
+    // Strict conversions check the value with $check before running the same conversion
+    (
+        $target_type:ty,
+        strict($checked: ident => $check:expr, $expected:ident),
+        ($value: ident) => $body:expr
+    ) => {
+        impl<'r: 'c, 'c> TryConvertJSValue<'r, 'c> for $target_type {
+            fn try_from_jsvalue($value: &JSValue<'_, '_>) -> EsperantoResult<Self> {
+                $body
+            }
+
+            fn try_from_jsvalue_strict($checked: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+                if $check == false {
+                    return Err(ConversionError::UnexpectedType {
+                        expected: JSValueType::$expected,
+                        actual: $checked.value_type(),
+                    }
+                    .into());
+                }
+                Self::try_from_jsvalue($checked)
+            }
+        }
+    };
 }
 
 impl<'r, 'c, Target> TryJSValueFrom<'r, 'c> for Target
 where
     Target: JSValueFrom<'r, 'c>,
-    'r: 'c,
 {
     fn try_jsvalue_from(
         value: Target,
@@ -68,9 +96,10 @@ where
 
 // String
 
+// Both directions go via WTF-8 (a superset of UTF-8) so that NUL characters survive
+
 try_to_js_value! {&str, (value, in_context) => {
-    let cstring = CString::new(value).map_err(|e| ConversionError::CouldNotConvertToJSString(e))?;
-    let ptr = JSValueInternalImpl::from_cstring(&cstring, in_context.implementation());
+    let ptr = JSValueInternalImpl::from_wtf8(value.as_bytes(), in_context.implementation())?;
     let val = JSValue::wrap_internal(ptr, in_context);
     Ok(Retain::wrap(val))
 }}
@@ -79,13 +108,14 @@ try_to_js_value! {String, (value, in_context) => {
     <&str>::try_jsvalue_from(value.as_str(), in_context)
 }}
 
-try_from_js_value! {String, (value) => {
-    let cstring = value.internal.as_cstring(value.context.implementation())?;
-    let str = cstring
-        .to_str()
-        .map_err(|e| ConversionError::CouldNotConvertFromJSString(e))?;
+try_from_js_value! {String, strict(value => value.is_string(), String), (value) => {
+    // Strings with unpaired surrogates aren't valid UTF-8, use JSValue::to_string_lossy()
+    // to convert them anyway.
+    let bytes = value.internal.as_wtf8(value.context.implementation())?;
+    let string = String::from_utf8(bytes)
+        .map_err(|e| ConversionError::CouldNotConvertFromJSString(e.utf8_error()))?;
 
-    Ok(str.to_owned())
+    Ok(string)
 }}
 
 // f64
@@ -96,22 +126,87 @@ try_to_js_value! {f64, (value, in_context) => {
     Ok(Retain::wrap(val))
 }}
 
-try_from_js_value! {f64, (value) => {
+try_from_js_value! {f64, strict(value => value.is_number(), Number), (value) => {
     let n = value.internal.as_number(value.context.implementation())?;
     Ok(n)
 }}
 
-// i32
+// f32
 
-try_to_js_value! {i32, (value, in_context) => {
+try_to_js_value! {f32, (value, in_context) => {
     f64::try_jsvalue_from(value as f64, in_context)
 }}
 
-try_from_js_value! {i32, (value) => {
+try_from_js_value! {f32, strict(value => value.is_number(), Number), (value) => {
     let n: f64 = value.try_convert()?;
-    Ok(n as i32)
+    // `as` saturates to infinity, which would quietly turn e.g. 1e300 into a different value.
+    // NaN and the infinities themselves convert fine.
+    let converted = n as f32;
+    if n.is_finite() && converted.is_infinite() {
+        return Err(ConversionError::CouldNotConvertToF32(n.to_string()).into());
+    }
+    Ok(converted)
 }}
 
+// Integers
+
+// The largest integer a JS number can hold without losing precision. Anything bigger
+// becomes a BigInt instead.
+const MAX_SAFE_INTEGER: i128 = 9_007_199_254_740_991;
+
+// i128 can hold every value of every integer type we convert, so we do all the checking
+// against it and leave the range check for the specific type to TryFrom.
+fn as_integer(value: &JSValue<'_, '_>) -> EsperantoResult<i128> {
+    if value.internal.is_bigint(value.context.implementation()) {
+        let digits: String = value.try_convert()?;
+        return digits
+            .parse()
+            .map_err(|_| ConversionError::CouldNotConvertToInteger(digits).into());
+    }
+
+    let n: f64 = value.try_convert()?;
+    if n.is_finite() == false || n.fract() != 0.0 {
+        return Err(ConversionError::CouldNotConvertToInteger(n.to_string()).into());
+    }
+    // Saturates if it's too big for an i128, which is too big for any of our types anyway
+    Ok(n as i128)
+}
+
+fn new_integer<'r, 'c>(value: i128, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+    if value.abs() <= MAX_SAFE_INTEGER {
+        return f64::try_jsvalue_from(value as f64, in_context);
+    }
+
+    let ctx = in_context.implementation();
+    let ptr = match i64::try_from(value) {
+        Ok(signed) => JSValueInternalImpl::from_i64_bigint(signed, ctx)?,
+        // Only ever called with 64-bit types, so if it isn't an i64 it must be a u64
+        Err(_) => JSValueInternalImpl::from_u64_bigint(value as u64, ctx)?,
+    };
+    let val = JSValue::wrap_internal(ptr, in_context);
+    Ok(Retain::wrap(val))
+}
+
+macro_rules! integer_conversions {
+    ($($integer_type:ty),+) => {$(
+        try_to_js_value! {$integer_type, (value, in_context) => {
+            new_integer(value as i128, in_context)
+        }}
+
+        try_from_js_value! {
+            $integer_type,
+            strict(value => value.is_number() || value.is_bigint(), Number),
+            (value) => {
+                let n = as_integer(value)?;
+                <$integer_type>::try_from(n)
+                    .map_err(|_| ConversionError::CouldNotConvertToInteger(n.to_string()).into())
+            }
+        }
+    )+};
+}
+
+integer_conversions!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
 // bool
 
 try_to_js_value! {bool, (value, in_context) => {
@@ -120,13 +215,262 @@ try_to_js_value! {bool, (value, in_context) => {
     Ok(Retain::wrap(val))
 }}
 
-try_from_js_value! {bool, (value) => {
+try_from_js_value! {bool, strict(value => value.is_bool(), Boolean), (value) => {
     value.internal.as_bool(value.context.implementation())
 }}
 
+// Arrays
+
+// Each item is converted and retained on the Rust side until the array has been created,
+// at which point the array holds on to them.
+fn new_array_from<'r, 'c, T, I>(items: I, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c>
+where
+    T: TryJSValueFrom<'r, 'c>,
+    I: Iterator<Item = T>,
+    'r: 'c,
+{
+    let converted = items
+        .map(|item| JSValue::try_new_from(item, in_context))
+        .collect::<EsperantoResult<Vec<_>>>()?;
+    JSValue::new_array(converted.iter().map(|item| &**item).collect(), in_context)
+}
+
+impl<'r, 'c, T> TryJSValueFrom<'r, 'c> for Vec<T>
+where
+    T: TryJSValueFrom<'r, 'c>,
+{
+    fn try_jsvalue_from(value: Self, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+        new_array_from(value.into_iter(), in_context)
+    }
+}
+
+impl<'r, 'c, 'a, T> TryJSValueFrom<'r, 'c> for &'a [T]
+where
+    T: TryJSValueFrom<'r, 'c> + Clone,
+{
+    fn try_jsvalue_from(value: Self, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+        new_array_from(value.iter().cloned(), in_context)
+    }
+}
+
+// Takes the conversion to run on each item so that strict conversions stay strict all the
+// way down
+fn items_from<'r, 'c, T, F>(value: &JSValue<'r, 'c>, convert: F) -> EsperantoResult<Vec<T>>
+where
+    F: Fn(&JSValue<'r, 'c>) -> EsperantoResult<T>,
+    'r: 'c,
+{
+    let length = value.array_length()?;
+    let mut items = Vec::with_capacity(length);
+    for index in 0..length {
+        let item = value.get_property_at_index(index as u32)?;
+        items.push(convert(&item)?);
+    }
+    Ok(items)
+}
+
+impl<'r: 'c, 'c, T> TryConvertJSValue<'r, 'c> for Vec<T>
+where
+    T: TryConvertJSValue<'r, 'c>,
+{
+    fn try_from_jsvalue(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        items_from(value, T::try_from_jsvalue)
+    }
+
+    fn try_from_jsvalue_strict(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        items_from(value, T::try_from_jsvalue_strict)
+    }
+}
+
+// Objects
+
+fn new_object_from<'r, 'c, T, I>(
+    entries: I,
+    in_context: &'c JSContext<'r, 'c>,
+) -> ValueResult<'r, 'c>
+where
+    T: TryJSValueFrom<'r, 'c>,
+    I: Iterator<Item = (String, T)>,
+    'r: 'c,
+{
+    let object = JSValue::new_object(in_context)?;
+    for (key, item) in entries {
+        let converted = JSValue::try_new_from(item, in_context)?;
+        object.set_property(&key, &converted)?;
+    }
+    Ok(object)
+}
+
+// Reads every enumerable property into whichever collection the caller wants
+fn entries_from<'r, 'c, T, C, F>(value: &JSValue<'r, 'c>, convert: F) -> EsperantoResult<C>
+where
+    C: FromIterator<(String, T)>,
+    F: Fn(&JSValue<'r, 'c>) -> EsperantoResult<T>,
+    'r: 'c,
+{
+    value
+        .own_property_names()?
+        .into_iter()
+        .map(|name| {
+            let item = value.get_property(&name)?;
+            Ok((name, convert(&item)?))
+        })
+        .collect()
+}
+
+impl<'r, 'c, T, S> TryJSValueFrom<'r, 'c> for HashMap<String, T, S>
+where
+    T: TryJSValueFrom<'r, 'c>,
+{
+    fn try_jsvalue_from(value: Self, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+        new_object_from(value.into_iter(), in_context)
+    }
+}
+
+impl<'r, 'c, T, S> TryConvertJSValue<'r, 'c> for HashMap<String, T, S>
+where
+    T: TryConvertJSValue<'r, 'c>,
+    S: BuildHasher + Default,
+    'r: 'c,
+{
+    fn try_from_jsvalue(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        entries_from(value, T::try_from_jsvalue)
+    }
+
+    fn try_from_jsvalue_strict(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        entries_from(value, T::try_from_jsvalue_strict)
+    }
+}
+
+impl<'r, 'c, T> TryJSValueFrom<'r, 'c> for BTreeMap<String, T>
+where
+    T: TryJSValueFrom<'r, 'c>,
+{
+    fn try_jsvalue_from(value: Self, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+        new_object_from(value.into_iter(), in_context)
+    }
+}
+
+impl<'r, 'c, T> TryConvertJSValue<'r, 'c> for BTreeMap<String, T>
+where
+    T: TryConvertJSValue<'r, 'c>,
+    'r: 'c,
+{
+    fn try_from_jsvalue(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        entries_from(value, T::try_from_jsvalue)
+    }
+
+    fn try_from_jsvalue_strict(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        entries_from(value, T::try_from_jsvalue_strict)
+    }
+}
+
+// Option and unit
+
+// Lets optional arguments and properties be left off entirely. JS code tends to use null and
+// undefined interchangeably for "nothing" so we accept either.
+impl<'r: 'c, 'c, T> TryConvertJSValue<'r, 'c> for Option<T>
+where
+    T: TryConvertJSValue<'r, 'c>,
+{
+    fn try_from_jsvalue(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        if value.is_undefined() || value.is_null() {
+            return Ok(None);
+        }
+        value.try_convert().map(Some)
+    }
+
+    fn try_from_jsvalue_strict(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        if value.is_undefined() || value.is_null() {
+            return Ok(None);
+        }
+        value.try_convert_strict().map(Some)
+    }
+}
+
+impl<'r, 'c, T> TryJSValueFrom<'r, 'c> for Option<T>
+where
+    T: TryJSValueFrom<'r, 'c>,
+{
+    fn try_jsvalue_from(value: Self, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+        match value {
+            Some(value) => JSValue::try_new_from(value, in_context),
+            None => Ok(JSValue::undefined(in_context)),
+        }
+    }
+}
+
+/// An `Option` that becomes null rather than undefined when it's `None`. Useful when JS code
+/// is checking for `=== null`, or when you're building something that'll be turned into JSON
+/// (which drops undefined properties entirely).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Nullable<T>(pub Option<T>);
+
+impl<T> From<Option<T>> for Nullable<T> {
+    fn from(value: Option<T>) -> Self {
+        Nullable(value)
+    }
+}
+
+impl<'r: 'c, 'c, T> TryConvertJSValue<'r, 'c> for Nullable<T>
+where
+    T: TryConvertJSValue<'r, 'c>,
+{
+    fn try_from_jsvalue(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        value.try_convert().map(Nullable)
+    }
+
+    fn try_from_jsvalue_strict(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        value.try_convert_strict().map(Nullable)
+    }
+}
+
+impl<'r, 'c, T> TryJSValueFrom<'r, 'c> for Nullable<T>
+where
+    T: TryJSValueFrom<'r, 'c>,
+{
+    fn try_jsvalue_from(value: Self, in_context: &'c JSContext<'r, 'c>) -> ValueResult<'r, 'c> {
+        match value.0 {
+            Some(value) => JSValue::try_new_from(value, in_context),
+            None => Ok(JSValue::null(in_context)),
+        }
+    }
+}
+
+/// Always converts strictly, even when used with `try_convert`. Mostly useful for the
+/// arguments of native functions, where you'd otherwise have no say in which kind of
+/// conversion is used:
+///
+/// ```ignore
+/// let (Strict(name),): (Strict<String>,) = FromJSArgs::from_js_args(args, ctx)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Strict<T>(pub T);
+
+impl<'r: 'c, 'c, T> TryConvertJSValue<'r, 'c> for Strict<T>
+where
+    T: TryConvertJSValue<'r, 'c>,
+{
+    fn try_from_jsvalue(value: &JSValue<'r, 'c>) -> EsperantoResult<Self> {
+        value.try_convert_strict().map(Strict)
+    }
+}
+
+// Lets native functions that don't return anything hand back undefined, same as a JS function
+impl<'r, 'c> JSValueFrom<'r, 'c> for () {
+    fn jsvalue_from(_: Self, in_context: &'c JSContext<'r, 'c>) -> Retain<JSValue<'r, 'c>> {
+        JSValue::undefined(in_context)
+    }
+}
+
 // Error
 
 try_to_js_value! {EsperantoError, (value, in_context) => {
+    // Errors that came from JS in this context get rethrown as they were
+    if let Some(thrown) = in_context.thrown_value(&value) {
+        return Ok(thrown);
+    }
+
     let (name, message): (&str, String) = match &value {
         Self::RuntimeError(err) => ("RuntimeError", err.to_string()),
         Self::CatchExceptionError(err) => ("CatchExceptionError", err.to_string()),
@@ -137,9 +481,7 @@ try_to_js_value! {EsperantoError, (value, in_context) => {
         Self::JavaScriptError(err) => (&err.name, err.message.to_string()),
     };
 
-    return Ok(Retain::wrap(JSValue::new_error(
-        name, &message, in_context,
-    )?));
+    return JSValue::new_error(name, &message, in_context);
 }}
 
 try_from_js_value! {JavaScriptError, (value) => {
@@ -186,8 +528,14 @@ try_from_js_value! {JavaScriptError, (value) => {
 
 #[cfg(test)]
 mod test {
+    use std::collections::{BTreeMap, HashMap};
+
     use crate::{
-        shared::{context::JSContextError, errors::JavaScriptError, value::TryConvertJSValue},
+        shared::{
+            context::JSContextError,
+            errors::{ConversionError, JavaScriptError},
+            value::{JSValueError, JSValueType, Nullable, Strict, TryConvertJSValue},
+        },
         EsperantoError, JSContext, JSValue,
     };
 
@@ -240,6 +588,108 @@ mod test {
         check_comparison!(1234, "1234");
     }
 
+    #[test]
+    fn converts_to_f32() {
+        check_eval!(f32, "12.5", 12.5);
+    }
+
+    #[test]
+    fn fails_to_convert_out_of_range_f32() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx.evaluate("-1e300", None).unwrap();
+        let result = value.try_convert::<f32>();
+        assert_eq!(
+            result.unwrap_err(),
+            ConversionError::CouldNotConvertToF32((-1e300f64).to_string()).into()
+        );
+
+        let value = ctx.evaluate("Infinity", None).unwrap();
+        assert_eq!(value.try_convert::<f32>().unwrap(), f32::INFINITY);
+        let value = ctx.evaluate("3.4028234663852886e38", None).unwrap();
+        assert_eq!(value.try_convert::<f32>().unwrap(), f32::MAX);
+    }
+
+    #[test]
+    fn converts_from_f32() {
+        check_comparison!(12.5f32, "12.5");
+    }
+
+    #[test]
+    fn converts_to_integers() {
+        check_eval!(u8, "255", 255);
+        check_eval!(i8, "-128", -128);
+        check_eval!(u16, "65535", 65535);
+        check_eval!(i32, "-2147483648", i32::MIN);
+        check_eval!(u32, "4294967295", u32::MAX);
+        check_eval!(usize, "1234", 1234);
+        check_eval!(i64, "-9007199254740991", -9007199254740991);
+    }
+
+    #[test]
+    fn converts_from_integers() {
+        check_comparison!(255u8, "255");
+        check_comparison!(-128i8, "-128");
+        check_comparison!(u32::MAX, "4294967295");
+        check_comparison!(-9007199254740991i64, "-9007199254740991");
+        check_comparison!(1234usize, "1234");
+    }
+
+    #[test]
+    fn fails_to_convert_out_of_range_integers() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx.evaluate("256", None).unwrap();
+        let result = value.try_convert::<u8>();
+        assert_eq!(
+            result.unwrap_err(),
+            ConversionError::CouldNotConvertToInteger("256".to_string()).into()
+        );
+
+        let value = ctx.evaluate("-1", None).unwrap();
+        assert!(value.try_convert::<u64>().is_err());
+    }
+
+    #[test]
+    fn fails_to_convert_non_integers() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx.evaluate("1.5", None).unwrap();
+        let result = value.try_convert::<i32>();
+        assert_eq!(
+            result.unwrap_err(),
+            ConversionError::CouldNotConvertToInteger("1.5".to_string()).into()
+        );
+
+        let value = ctx.evaluate("NaN", None).unwrap();
+        assert!(value.try_convert::<i32>().is_err());
+    }
+
+    #[test]
+    fn converts_large_64_bit_integers_to_bigint() {
+        check_comparison!(9007199254740993i64, "9007199254740993n");
+        check_comparison!(i64::MIN, "-9223372036854775808n");
+        check_comparison!(u64::MAX, "18446744073709551615n");
+    }
+
+    #[test]
+    fn converts_to_bigint_when_global_is_replaced() {
+        let ctx = JSContext::new().unwrap();
+        ctx.evaluate("globalThis.BigInt = () => 0", None).unwrap();
+        let converted = JSValue::try_new_from(u64::MAX, &ctx).unwrap();
+        assert!(converted.is_bigint());
+        assert_eq!(converted.try_convert::<u64>().unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn converts_bigint_to_64_bit_integers() {
+        check_eval!(i64, "9007199254740993n", 9007199254740993);
+        check_eval!(i64, "-9223372036854775808n", i64::MIN);
+        check_eval!(u64, "18446744073709551615n", u64::MAX);
+        check_eval!(u8, "12n", 12);
+
+        let ctx = JSContext::new().unwrap();
+        let value = ctx.evaluate("18446744073709551616n", None).unwrap();
+        assert!(value.try_convert::<u64>().is_err());
+    }
+
     #[test]
     fn converts_to_string() {
         check_eval!(String, "'hello'", "hello");
@@ -250,6 +700,176 @@ mod test {
         check_comparison!("hello", "'hello'");
     }
 
+    #[test]
+    fn converts_to_vec() {
+        check_eval!(Vec<i32>, "[1, 2, 3]", vec![1, 2, 3]);
+        check_eval!(Vec<String>, "['a', 'b']", vec!["a", "b"]);
+        check_eval!(Vec<Vec<f64>>, "[[1.5], []]", vec![vec![1.5], vec![]]);
+    }
+
+    #[test]
+    fn converts_from_vec() {
+        let ctx = JSContext::new().unwrap();
+        let check_joined = |value: &JSValue, expected: &str| {
+            let join = value.get_property("join").unwrap();
+            let joined = join.call_as_function_bound(vec![], Some(value)).unwrap();
+            assert_eq!(joined.try_convert::<String>().unwrap(), expected);
+        };
+
+        check_joined(
+            &JSValue::try_new_from(vec![1, 2, 3], &ctx).unwrap(),
+            "1,2,3",
+        );
+        check_joined(&JSValue::try_new_from(vec!["a", "b"], &ctx).unwrap(), "a,b");
+        check_joined(
+            &JSValue::try_new_from(&[true, false][..], &ctx).unwrap(),
+            "true,false",
+        );
+    }
+
+    #[test]
+    fn fails_to_convert_non_array_to_vec() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx.evaluate("({0: 1, length: 1})", None).unwrap();
+        assert_eq!(
+            value.try_convert::<Vec<i32>>().unwrap_err(),
+            JSValueError::IsNotAnArray.into()
+        );
+    }
+
+    #[test]
+    fn converts_to_maps() {
+        let mut expected = HashMap::new();
+        expected.insert("one".to_string(), 1);
+        expected.insert("two".to_string(), 2);
+        check_eval!(HashMap<String, i32>, "({one: 1, two: 2})", expected);
+
+        let mut expected = BTreeMap::new();
+        expected.insert("nested".to_string(), vec!["a".to_string()]);
+        check_eval!(BTreeMap<String, Vec<String>>, "({nested: ['a']})", expected);
+    }
+
+    #[test]
+    fn converts_to_maps_with_nul_in_keys() {
+        let mut expected = HashMap::new();
+        expected.insert("a\0b".to_string(), Some(1));
+        expected.insert("c".to_string(), Some(2));
+        check_eval!(HashMap<String, Option<i32>>, "({'a\\u0000b': 1, c: 2})", expected);
+    }
+
+    #[test]
+    fn converts_from_maps() {
+        let ctx = JSContext::new().unwrap();
+        let mut map = BTreeMap::new();
+        map.insert("one".to_string(), 1);
+        map.insert("two".to_string(), 2);
+
+        let converted = JSValue::try_new_from(map.clone(), &ctx).unwrap();
+        assert_eq!(converted.own_property_names().unwrap(), vec!["one", "two"]);
+        assert_eq!(
+            converted
+                .get_property("two")
+                .unwrap()
+                .try_convert::<i32>()
+                .unwrap(),
+            2
+        );
+
+        let round_tripped: HashMap<String, i32> = converted.try_convert().unwrap();
+        assert_eq!(round_tripped, map.into_iter().collect());
+    }
+
+    #[test]
+    fn fails_to_convert_non_object_to_map() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx.evaluate("1", None).unwrap();
+        let result = value.try_convert::<HashMap<String, i32>>();
+        assert_eq!(result.unwrap_err(), JSValueError::IsNotAnObject.into());
+    }
+
+    #[test]
+    fn converts_null_and_undefined_to_none() {
+        check_eval!(Option<f64>, "undefined", None);
+        check_eval!(Option<f64>, "null", None);
+        check_eval!(Option<f64>, "1.5", Some(1.5));
+        check_eval!(Nullable<String>, "null", Nullable(None));
+    }
+
+    #[test]
+    fn converts_from_option() {
+        check_comparison!(Some(1.5), "1.5");
+        check_comparison!(None::<f64>, "undefined");
+        check_comparison!(Nullable(Some("hello")), "'hello'");
+        check_comparison!(Nullable(None::<f64>), "null");
+    }
+
+    #[test]
+    fn converts_from_unit() {
+        check_comparison!((), "undefined");
+    }
+
+    #[test]
+    fn coerces_values_of_the_wrong_type() {
+        check_eval!(String, "({})", "[object Object]");
+        check_eval!(f64, "'12.5'", 12.5);
+        check_eval!(bool, "'false'", true);
+        check_eval!(i32, "'12'", 12);
+    }
+
+    #[test]
+    fn strictly_converts_values_of_the_right_type() {
+        let ctx = JSContext::new().unwrap();
+        let check = |script: &str| ctx.evaluate(script, None).unwrap();
+
+        assert_eq!(
+            check("'hello'").try_convert_strict::<String>().unwrap(),
+            "hello"
+        );
+        assert_eq!(check("12.5").try_convert_strict::<f64>().unwrap(), 12.5);
+        assert_eq!(check("false").try_convert_strict::<bool>().unwrap(), false);
+        assert_eq!(check("12n").try_convert_strict::<u8>().unwrap(), 12);
+        assert_eq!(
+            check("[1, null]")
+                .try_convert_strict::<Vec<Option<i32>>>()
+                .unwrap(),
+            vec![Some(1), None]
+        );
+    }
+
+    #[test]
+    fn strictly_rejects_values_of_the_wrong_type() {
+        let ctx = JSContext::new().unwrap();
+        let check = |script: &str| ctx.evaluate(script, None).unwrap();
+        let unexpected = |expected, actual| -> EsperantoError {
+            ConversionError::UnexpectedType { expected, actual }.into()
+        };
+
+        assert_eq!(
+            check("({})").try_convert_strict::<String>().unwrap_err(),
+            unexpected(JSValueType::String, JSValueType::Object)
+        );
+        assert_eq!(
+            check("'12.5'").try_convert_strict::<f64>().unwrap_err(),
+            unexpected(JSValueType::Number, JSValueType::String)
+        );
+        assert_eq!(
+            check("1").try_convert_strict::<bool>().unwrap_err(),
+            unexpected(JSValueType::Boolean, JSValueType::Number)
+        );
+        assert_eq!(
+            check("[1, '2']")
+                .try_convert_strict::<Vec<i32>>()
+                .unwrap_err(),
+            unexpected(JSValueType::Number, JSValueType::String)
+        );
+        assert_eq!(
+            check("({one: true})")
+                .try_convert::<Strict<HashMap<String, String>>>()
+                .unwrap_err(),
+            unexpected(JSValueType::String, JSValueType::Boolean)
+        );
+    }
+
     #[test]
     fn converts_from_js_error() {
         let ctx = JSContext::new().unwrap();
@@ -8,6 +8,12 @@ pub enum JSValueError {
     #[error("This operation requires the JSValue to be an object, but you have provided a value")]
     IsNotAnObject,
 
+    #[error("This operation requires the JSValue to be an array")]
+    IsNotAnArray,
+
+    #[error("This operation requires the JSValue to be iterable")]
+    IsNotIterable,
+
     #[error("This value is not a number")]
     IsNotANumber,
 
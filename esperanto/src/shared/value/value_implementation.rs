@@ -1,15 +1,39 @@
 use std::ffi::{c_void, CStr, CString};
 
-use crate::shared::context::JSContextImplementation;
+use crate::shared::context::{Intrinsic, JSContextImplementation};
 use crate::shared::errors::{EsperantoError, EsperantoResult, JavaScriptError};
-use crate::JSExportClass;
+use crate::shared::util::string_from_wtf8_lossy;
+use crate::{JSContext, JSExportClass, JSValue, Retain};
+
+use super::PropertyKey;
+
+/// The closures passed to JSValue::new_native_function. Engines store these boxed as private
+/// data on the function object and drop them when it's finalized.
+pub(crate) type NativeFunction = dyn for<'r, 'c, 'v> Fn(
+    &'c JSContext<'r, 'c>,
+    &'v JSValue<'r, 'c>,
+    &'v [&'v JSValue<'r, 'c>],
+)
+    -> EsperantoResult<Retain<JSValue<'r, 'c>>>;
 
 pub(crate) trait JSValueImplementation: Sized + Copy {
     type ContextType: JSContextImplementation + Copy;
 
     fn as_cstring(self, ctx: Self::ContextType) -> EsperantoResult<CString>;
     fn from_cstring(value: &CString, ctx: Self::ContextType) -> Self;
+    /// Unlike the CString versions these don't stop at NUL characters. WTF-8 is UTF-8 that can
+    /// also hold the unpaired surrogates JS strings are allowed to contain.
+    fn as_utf16(self, ctx: Self::ContextType) -> EsperantoResult<Vec<u16>>;
+    fn from_utf16(value: &[u16], ctx: Self::ContextType) -> EsperantoResult<Self>;
+    fn as_wtf8(self, ctx: Self::ContextType) -> EsperantoResult<Vec<u8>>;
+    fn from_wtf8(value: &[u8], ctx: Self::ContextType) -> EsperantoResult<Self>;
     fn is_string(self, ctx: Self::ContextType) -> bool;
+    /// Returns None for values JSON has no representation of, like undefined and functions.
+    fn to_json_string(
+        self,
+        indent: u32,
+        ctx: Self::ContextType,
+    ) -> EsperantoResult<Option<CString>>;
 
     fn as_number(self, ctx: Self::ContextType) -> EsperantoResult<f64>;
     fn from_number(number: f64, ctx: Self::ContextType) -> EsperantoResult<Self>;
@@ -17,32 +41,76 @@ pub(crate) trait JSValueImplementation: Sized + Copy {
     fn as_bool(self, ctx: Self::ContextType) -> EsperantoResult<bool>;
     fn from_bool(bool: bool, ctx: Self::ContextType) -> EsperantoResult<Self>;
 
+    /// Only used for 64-bit integers that are too big to be represented exactly by a number.
+    fn is_bigint(self, ctx: Self::ContextType) -> bool;
+    fn from_i64_bigint(value: i64, ctx: Self::ContextType) -> EsperantoResult<Self>;
+    fn from_u64_bigint(value: u64, ctx: Self::ContextType) -> EsperantoResult<Self>;
+
     fn new_error(name: CString, message: CString, ctx: Self::ContextType) -> Self;
     fn is_error(self, ctx: Self::ContextType) -> EsperantoResult<bool>;
 
-    fn to_js_error(self, ctx: Self::ContextType) -> EsperantoResult<JavaScriptError> {
-        const NAME_PROP_STR: &[u8] = b"name\0";
-        const MESSAGE_PROP_STR: &[u8] = b"message\0";
-
-        let name =
-            unsafe { self.get_property(ctx, CStr::from_ptr(NAME_PROP_STR.as_ptr() as *const i8))? };
-        let message = unsafe {
-            self.get_property(ctx, CStr::from_ptr(MESSAGE_PROP_STR.as_ptr() as *const i8))?
-        };
+    /// Never fails, since anything can be thrown. Whatever we can't read or convert is left
+    /// out of the summary.
+    fn to_js_error(self, ctx: Self::ContextType) -> JavaScriptError {
+        js_error_from(self, ctx, 0)
+    }
 
-        let name_str = name.as_cstring(ctx)?;
-        let message_str = message.as_cstring(ctx)?;
+    /// Where an error object was created. There's no standard for this so each engine reads
+    /// it from its own properties.
+    fn error_location(self, ctx: Self::ContextType) -> EsperantoResult<ErrorLocation>;
 
-        name.release(ctx);
-        message.release(ctx);
+    /// Reads a property as a string, treating undefined and null as missing.
+    fn get_optional_string_property(
+        self,
+        ctx: Self::ContextType,
+        name: &CStr,
+    ) -> EsperantoResult<Option<String>> {
+        let value = self.get_property(ctx, name)?;
+        let result = if value.is_undefined(ctx) || value.is_null(ctx) {
+            Ok(None)
+        } else {
+            value
+                .as_wtf8(ctx)
+                .map(|bytes| Some(string_from_wtf8_lossy(&bytes)))
+        };
+        value.release(ctx);
+        result
+    }
 
-        Ok(JavaScriptError::new(
-            name_str.to_string_lossy().into_owned(),
-            message_str.to_string_lossy().into_owned(),
-        ))
+    /// Reads a property as a line or column number, treating anything that isn't a
+    /// non-negative number as missing.
+    fn get_optional_position_property(
+        self,
+        ctx: Self::ContextType,
+        name: &CStr,
+    ) -> EsperantoResult<Option<u32>> {
+        let value = self.get_property(ctx, name)?;
+        let result = if value.is_number(ctx) {
+            value.as_number(ctx).map(|number| {
+                if number >= 0.0 {
+                    Some(number as u32)
+                } else {
+                    None
+                }
+            })
+        } else {
+            Ok(None)
+        };
+        value.release(ctx);
+        result
     }
 
     fn undefined(ctx: Self::ContextType) -> Self;
+    fn null(ctx: Self::ContextType) -> Self;
+    fn is_undefined(self, ctx: Self::ContextType) -> bool;
+    fn is_null(self, ctx: Self::ContextType) -> bool;
+    fn is_bool(self, ctx: Self::ContextType) -> bool;
+    fn is_number(self, ctx: Self::ContextType) -> bool;
+    fn is_function(self, ctx: Self::ContextType) -> bool;
+    fn is_symbol(self, ctx: Self::ContextType) -> bool;
+    fn new_symbol(description: Option<&str>, ctx: Self::ContextType) -> EsperantoResult<Self>;
+    /// The captured built-in, not retained. See get_intrinsic.
+    fn intrinsic(intrinsic: Intrinsic, ctx: Self::ContextType) -> EsperantoResult<Self>;
 
     fn native_prototype_for<'r: 'c, 'c, T: JSExportClass>(
         ctx: Self::ContextType,
@@ -51,14 +119,25 @@ pub(crate) trait JSValueImplementation: Sized + Copy {
 
     // fn constructor_for<T: JSExportClass>(wrapped_ctx: &JSContext) -> EsperantoResult<Self>;
 
+    fn new_native_function(
+        func: Box<NativeFunction>,
+        ctx: Self::ContextType,
+        runtime: &<Self::ContextType as JSContextImplementation>::RuntimeType,
+    ) -> EsperantoResult<Self>;
+
     fn from_native_class<T: JSExportClass>(
         instance: T,
         ctx: Self::ContextType,
         runtime: &<Self::ContextType as JSContextImplementation>::RuntimeType,
     ) -> EsperantoResult<Self>;
 
-    fn get_native_ref<'a, T: JSExportClass>(self, ctx: Self::ContextType)
-        -> EsperantoResult<&'a T>;
+    /// Returns the private data of a wrapped native object, as long as it was created from
+    /// T or a subclass of T. JSExportPrivateData checks which one it actually is.
+    fn get_native_ptr<T: JSExportClass>(
+        self,
+        ctx: Self::ContextType,
+        runtime: &<Self::ContextType as JSContextImplementation>::RuntimeType,
+    ) -> EsperantoResult<*mut c_void>;
 
     fn release(self, ctx: Self::ContextType);
 
@@ -74,7 +153,38 @@ pub(crate) trait JSValueImplementation: Sized + Copy {
 
     fn get_property(self, ctx: Self::ContextType, name: &CStr) -> Result<Self, EsperantoError>;
 
-    fn delete_property(self, ctx: Self::ContextType, name: &CStr) -> EsperantoResult<bool>;
+    // The CStr versions above are for the fixed names we use internally, these are what
+    // JSValue's public property methods go through.
+    fn get_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+    ) -> EsperantoResult<Self>;
+    fn set_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+        new_value: Self,
+    ) -> EsperantoResult<()>;
+    fn delete_property_for_key(
+        self,
+        ctx: Self::ContextType,
+        key: PropertyKey<'_, '_, '_>,
+    ) -> EsperantoResult<bool>;
+
+    fn new_object(ctx: Self::ContextType) -> EsperantoResult<Self>;
+    /// The names of the object's own enumerable, string-keyed properties, as WTF-8.
+    fn own_property_names(self, ctx: Self::ContextType) -> EsperantoResult<Vec<Vec<u8>>>;
+
+    fn new_array(values: Vec<Self>, ctx: Self::ContextType) -> EsperantoResult<Self>;
+    fn is_array(self, ctx: Self::ContextType) -> EsperantoResult<bool>;
+    fn get_property_at_index(self, ctx: Self::ContextType, index: u32) -> EsperantoResult<Self>;
+    fn set_property_at_index(
+        self,
+        ctx: Self::ContextType,
+        index: u32,
+        new_value: Self,
+    ) -> EsperantoResult<()>;
 
     fn new_function(
         function_text: &CString,
@@ -102,3 +212,99 @@ pub(crate) trait JSValueImplementation: Sized + Copy {
     fn get_private_data(self, ctx: Self::ContextType) -> EsperantoResult<*mut c_void>;
     fn set_private_data(self, ctx: Self::ContextType, data: *mut c_void) -> EsperantoResult<()>;
 }
+
+/// Where an error was created, as returned by JSValueImplementation::error_location.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct ErrorLocation {
+    pub source_url: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+// Nothing stops a JS error from being its own cause (or a longer cycle) so we give up
+// following the chain after this many levels.
+const MAX_CAUSE_DEPTH: usize = 32;
+
+fn js_error_from<Value: JSValueImplementation>(
+    value: Value,
+    ctx: Value::ContextType,
+    depth: usize,
+) -> JavaScriptError {
+    const NAME_PROP: &[u8] = b"name\0";
+    const MESSAGE_PROP: &[u8] = b"message\0";
+    const STACK_PROP: &[u8] = b"stack\0";
+    const CAUSE_PROP: &[u8] = b"cause\0";
+
+    if value.is_object(ctx) == false {
+        // Anything can be thrown (or be a cause), e.g. `throw "oops"`. There's no name to read
+        // in that case, so the value itself becomes the message.
+        return JavaScriptError::new(String::new(), primitive_description(value, ctx));
+    }
+
+    let property_name = |bytes: &'static [u8]| CStr::from_bytes_with_nul(bytes).unwrap();
+    // Reading a property can run a getter, and a getter that throws just means we go without
+    let optional_string = |name: &'static [u8]| {
+        value
+            .get_optional_string_property(ctx, property_name(name))
+            .unwrap_or_default()
+    };
+
+    // Plain objects (e.g. `throw {code: 42}`) have no message, so we fall back to what
+    // String(value) gives rather than leaving nothing to display
+    let message = optional_string(MESSAGE_PROP).unwrap_or_else(|| {
+        value
+            .as_wtf8(ctx)
+            .map(|string| string_from_wtf8_lossy(&string))
+            .unwrap_or_else(|_| "Uncaught exception".to_string())
+    });
+    let mut error = JavaScriptError::new(optional_string(NAME_PROP).unwrap_or_default(), message);
+    error.stack = optional_string(STACK_PROP);
+
+    let location = value.error_location(ctx).unwrap_or_default();
+    error.source_url = location.source_url;
+    error.line = location.line;
+    error.column = location.column;
+
+    if depth < MAX_CAUSE_DEPTH {
+        error.cause = value
+            .get_property(ctx, property_name(CAUSE_PROP))
+            .ok()
+            .and_then(|cause| {
+                let cause_error = if cause.is_undefined(ctx) {
+                    None
+                } else {
+                    Some(Box::new(js_error_from(cause, ctx, depth + 1)))
+                };
+                cause.release(ctx);
+                cause_error
+            });
+    }
+
+    error
+}
+
+// Symbols are the one primitive that throws when converted to a string, so for those we do
+// what String(symbol) does instead and use their description.
+fn primitive_description<Value: JSValueImplementation>(
+    value: Value,
+    ctx: Value::ContextType,
+) -> String {
+    let string = match value.is_symbol(ctx) {
+        true => symbol_to_string(value, ctx),
+        false => value.as_wtf8(ctx),
+    };
+    string
+        .map(|string| string_from_wtf8_lossy(&string))
+        .unwrap_or_default()
+}
+
+fn symbol_to_string<Value: JSValueImplementation>(
+    value: Value,
+    ctx: Value::ContextType,
+) -> EsperantoResult<Vec<u8>> {
+    let string_function = Value::intrinsic(Intrinsic::String, ctx)?;
+    let string = string_function.call_as_function(vec![value], None, ctx)?;
+    let result = string.as_wtf8(ctx);
+    string.release(ctx);
+    result
+}
@@ -0,0 +1,95 @@
+use crate::shared::errors::EsperantoResult;
+use crate::{JSValue, Retain};
+
+use super::JSValueError;
+
+/// Iterates over a JS iterable (arrays, Maps, Sets, generators and anything else with a
+/// `[Symbol.iterator]()` method) the same way `for...of` would. Created by `JSValue::iter`.
+///
+/// If the iterator is dropped before it's finished the JS iterator's `return()` method is
+/// called, just like breaking out of a `for...of` loop, so that generators get to run their
+/// `finally` blocks. An error ends the iteration.
+pub struct JSValueIterator<'r, 'c> {
+    iterator: Retain<JSValue<'r, 'c>>,
+    next: Retain<JSValue<'r, 'c>>,
+    done: bool,
+}
+
+impl<'r: 'c, 'c> JSValueIterator<'r, 'c> {
+    pub(crate) fn new(
+        iterable: &JSValue<'r, 'c>,
+        iterator_symbol: &JSValue<'r, 'c>,
+    ) -> EsperantoResult<Self> {
+        let iterator_method = iterable.get_property(iterator_symbol)?;
+        if iterator_method.is_function() == false {
+            return Err(JSValueError::IsNotIterable.into());
+        }
+
+        let iterator = iterator_method.call_as_function_bound(vec![], Some(iterable))?;
+        if iterator.is_object() == false {
+            return Err(JSValueError::IsNotAnObject.into());
+        }
+        // Same as for...of, we only look up next() once
+        let next = iterator.get_property("next")?;
+
+        Ok(JSValueIterator {
+            iterator,
+            next,
+            done: false,
+        })
+    }
+
+    fn step(&self) -> EsperantoResult<Option<Retain<JSValue<'r, 'c>>>> {
+        let result = self
+            .next
+            .call_as_function_bound(vec![], Some(&self.iterator))?;
+        if result.is_object() == false {
+            return Err(JSValueError::IsNotAnObject.into());
+        }
+
+        let done: bool = result.get_property("done")?.try_convert()?;
+        if done {
+            return Ok(None);
+        }
+        result.get_property("value").map(Some)
+    }
+}
+
+impl<'r: 'c, 'c> Iterator for JSValueIterator<'r, 'c> {
+    type Item = EsperantoResult<Retain<JSValue<'r, 'c>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.step() {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                // The JS iterator is in an unknown state, so we don't call return() either
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+impl Drop for JSValueIterator<'_, '_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        // return() is optional, and there's nothing useful we can do with an error from it
+        // while dropping.
+        if let Ok(return_method) = self.iterator.get_property("return") {
+            if return_method.is_function() {
+                let _ = return_method.call_as_function_bound(vec![], Some(&self.iterator));
+            }
+        }
+    }
+}
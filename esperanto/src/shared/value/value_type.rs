@@ -0,0 +1,14 @@
+/// The type of a JSValue, i.e. roughly what `typeof` would tell you in JS. The one exception
+/// is null, which gets its own type rather than being reported as an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JSValueType {
+    Undefined,
+    Null,
+    Boolean,
+    Number,
+    BigInt,
+    String,
+    Symbol,
+    Function,
+    Object,
+}
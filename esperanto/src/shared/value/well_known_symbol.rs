@@ -0,0 +1,57 @@
+/// The symbols JS uses to let objects customise built-in behaviour, e.g. `Symbol.iterator`
+/// to make an object iterable or `Symbol.toStringTag` to change what
+/// `Object.prototype.toString()` returns for it. Get the actual symbol value with
+/// `JSValue::well_known_symbol`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WellKnownSymbol {
+    AsyncIterator,
+    HasInstance,
+    IsConcatSpreadable,
+    Iterator,
+    Match,
+    MatchAll,
+    Replace,
+    Search,
+    Species,
+    Split,
+    ToPrimitive,
+    ToStringTag,
+    Unscopables,
+}
+
+impl WellKnownSymbol {
+    pub(crate) const ALL: [WellKnownSymbol; 13] = [
+        WellKnownSymbol::AsyncIterator,
+        WellKnownSymbol::HasInstance,
+        WellKnownSymbol::IsConcatSpreadable,
+        WellKnownSymbol::Iterator,
+        WellKnownSymbol::Match,
+        WellKnownSymbol::MatchAll,
+        WellKnownSymbol::Replace,
+        WellKnownSymbol::Search,
+        WellKnownSymbol::Species,
+        WellKnownSymbol::Split,
+        WellKnownSymbol::ToPrimitive,
+        WellKnownSymbol::ToStringTag,
+        WellKnownSymbol::Unscopables,
+    ];
+
+    /// The name of the property on the Symbol constructor that holds this symbol
+    pub(crate) fn property_name(self) -> &'static str {
+        match self {
+            WellKnownSymbol::AsyncIterator => "asyncIterator",
+            WellKnownSymbol::HasInstance => "hasInstance",
+            WellKnownSymbol::IsConcatSpreadable => "isConcatSpreadable",
+            WellKnownSymbol::Iterator => "iterator",
+            WellKnownSymbol::Match => "match",
+            WellKnownSymbol::MatchAll => "matchAll",
+            WellKnownSymbol::Replace => "replace",
+            WellKnownSymbol::Search => "search",
+            WellKnownSymbol::Species => "species",
+            WellKnownSymbol::Split => "split",
+            WellKnownSymbol::ToPrimitive => "toPrimitive",
+            WellKnownSymbol::ToStringTag => "toStringTag",
+            WellKnownSymbol::Unscopables => "unscopables",
+        }
+    }
+}
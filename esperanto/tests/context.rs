@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod test {
 
-    use esperanto::{EsperantoError, JSContext};
+    use esperanto::errors::JavaScriptError;
+    use esperanto::{EsperantoError, EvaluateMetadata, JSContext};
 
     #[test]
     fn creates_context_successfully() {
@@ -50,4 +51,274 @@ mod test {
             _ => panic!("Unexpected error type"),
         }
     }
+
+    #[test]
+    fn catches_error_locations_and_stacks() {
+        let ctx = JSContext::new().unwrap();
+        let metadata = EvaluateMetadata::new("test.js", 1).unwrap();
+        let script = "function fails() {\n  throw new Error('woah')\n}\nfails()";
+        let result = ctx.evaluate(script, Some(&metadata)).unwrap_err();
+        match result {
+            EsperantoError::JavaScriptError(err) => {
+                assert_eq!(err.source_url.as_deref(), Some("test.js"));
+                assert_eq!(err.line, Some(2));
+                assert!(err.stack.unwrap().contains("fails"));
+            }
+            _ => panic!("Unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn catches_error_causes() {
+        let ctx = JSContext::new().unwrap();
+        // Not every engine supports the `cause` constructor option yet, but the property is
+        // all we read.
+        let script = "\
+            const root = new TypeError('root'); \
+            const middle = Object.assign(new Error('middle'), { cause: root }); \
+            throw Object.assign(new RangeError('top'), { cause: middle })";
+        let result = ctx.evaluate(script, None).unwrap_err();
+        match result {
+            EsperantoError::JavaScriptError(err) => {
+                assert_eq!(err.message, "top");
+                let middle = err.cause.unwrap();
+                assert_eq!(middle.name, "Error");
+                assert_eq!(middle.message, "middle");
+                let root = middle.cause.unwrap();
+                assert_eq!(root.name, "TypeError");
+                assert_eq!(root.message, "root");
+                assert!(root.cause.is_none());
+            }
+            _ => panic!("Unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn stops_following_circular_causes() {
+        let ctx = JSContext::new().unwrap();
+        let script = "const err = new Error('loop'); err.cause = err; throw err";
+        let result = ctx.evaluate(script, None).unwrap_err();
+        match result {
+            EsperantoError::JavaScriptError(err) => {
+                let mut depth = 0;
+                let mut current = &err;
+                while let Some(cause) = &current.cause {
+                    depth += 1;
+                    current = cause;
+                }
+                assert!(depth > 0 && depth < 100);
+            }
+            _ => panic!("Unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn converts_non_error_causes() {
+        let ctx = JSContext::new().unwrap();
+        let result = ctx
+            .evaluate(
+                "throw Object.assign(new Error('outer'), { cause: 'a string' })",
+                None,
+            )
+            .unwrap_err();
+        match result {
+            EsperantoError::JavaScriptError(err) => {
+                let cause = err.cause.unwrap();
+                assert_eq!(cause.name, "");
+                assert_eq!(cause.message, "a string");
+            }
+            _ => panic!("Unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn keeps_errors_with_unconvertible_causes() {
+        let ctx = JSContext::new().unwrap();
+        for script in &[
+            "var e = new Error('a'); e.cause = Symbol('c'); throw e",
+            "var e = new Error('a'); \
+             Object.defineProperty(e, 'cause', { get() { throw new Error('b') } }); \
+             throw e",
+        ] {
+            match ctx.evaluate(script, None).unwrap_err() {
+                EsperantoError::JavaScriptError(err) => {
+                    assert_eq!(err.name, "Error");
+                    assert_eq!(err.message, "a");
+                    assert!(err.cause.is_none() || err.cause.unwrap().message == "Symbol(c)");
+                }
+                err => panic!("Unexpected error {:?}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_thrown_non_error_values() {
+        let ctx = JSContext::new().unwrap();
+        let err = ctx
+            .evaluate("throw { code: 42, detail: 'not found' }", None)
+            .unwrap_err();
+
+        let thrown = ctx.thrown_value(&err).unwrap();
+        let code: f64 = thrown.get_property("code").unwrap().try_convert().unwrap();
+        let detail: String = thrown
+            .get_property("detail")
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(code, 42.0);
+        assert_eq!(detail, "not found");
+    }
+
+    #[test]
+    fn catches_thrown_primitives() {
+        let ctx = JSContext::new().unwrap();
+        let err = ctx.evaluate("throw 'oops'", None).unwrap_err();
+        assert_eq!(err.to_string(), "oops");
+
+        let thrown: String = ctx.thrown_value(&err).unwrap().try_convert().unwrap();
+        assert_eq!(thrown, "oops");
+    }
+
+    #[test]
+    fn catches_thrown_null() {
+        let ctx = JSContext::new().unwrap();
+        let err = ctx.evaluate("throw null", None).unwrap_err();
+        assert_eq!(err.to_string(), "null");
+        assert!(ctx.thrown_value(&err).unwrap().is_null());
+    }
+
+    #[test]
+    fn describes_thrown_plain_objects() {
+        let ctx = JSContext::new().unwrap();
+        let err = ctx.evaluate("throw {code: 42}", None).unwrap_err();
+        assert_eq!(err.to_string(), "[object Object]");
+        let thrown = ctx.thrown_value(&err).unwrap();
+        let code: f64 = thrown.get_property("code").unwrap().try_convert().unwrap();
+        assert_eq!(code, 42.0);
+
+        let err = ctx
+            .evaluate("throw {toString() { return 'custom' }}", None)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "custom");
+
+        let err = ctx
+            .evaluate("throw {toString() { throw 'no' }}", None)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Uncaught exception");
+    }
+
+    #[test]
+    fn catches_thrown_symbols() {
+        let ctx = JSContext::new().unwrap();
+        let err = ctx.evaluate("throw Symbol('x')", None).unwrap_err();
+        assert_eq!(err.to_string(), "Symbol(x)");
+        assert!(ctx.thrown_value(&err).unwrap().is_symbol());
+
+        let err = ctx.evaluate("throw Symbol()", None).unwrap_err();
+        assert_eq!(err.to_string(), "Symbol()");
+        assert!(ctx.thrown_value(&err).unwrap().is_symbol());
+    }
+
+    #[test]
+    fn keeps_thrown_values_with_throwing_getters() {
+        let ctx = JSContext::new().unwrap();
+        let err = ctx
+            .evaluate(
+                "globalThis.original = { get name() { throw 'inner' }, message: 'outer' }; \
+                 throw original",
+                None,
+            )
+            .unwrap_err();
+        match &err {
+            EsperantoError::JavaScriptError(js_err) => {
+                assert_eq!(js_err.name, "");
+                assert_eq!(js_err.message, "outer");
+            }
+            _ => panic!("Unexpected error type"),
+        }
+        let original = ctx.evaluate("original", None).unwrap();
+        assert_eq!(ctx.thrown_value(&err).unwrap(), original);
+
+        // Getters that keep throwing themselves can't send us into a loop
+        let err = ctx
+            .evaluate("const o = { get message() { throw o } }; throw o", None)
+            .unwrap_err();
+        assert!(ctx.thrown_value(&err).unwrap().is_object());
+    }
+
+    #[test]
+    fn errors_compare_equal_without_their_thrown_values() {
+        let ctx = JSContext::new().unwrap();
+        let script = "throw new TypeError('bad')";
+        let first = ctx.evaluate(script, None).unwrap_err();
+        let second = ctx.evaluate(script, None).unwrap_err();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn thrown_value_is_the_original_object() {
+        let ctx = JSContext::new().unwrap();
+        let err = ctx
+            .evaluate(
+                "globalThis.original = new TypeError('bad'); throw original",
+                None,
+            )
+            .unwrap_err();
+
+        let original = ctx.global_object().get_property("original").unwrap();
+        assert!(*ctx.thrown_value(&err).unwrap() == *original);
+    }
+
+    #[test]
+    fn thrown_values_only_resolve_in_their_own_context() {
+        let ctx = JSContext::new().unwrap();
+        let other_ctx = JSContext::new().unwrap();
+        let err = ctx.evaluate("throw new Error('woah')", None).unwrap_err();
+        assert!(other_ctx.thrown_value(&err).is_none());
+
+        let native_err: EsperantoError = JavaScriptError::new("Error".into(), "woah".into()).into();
+        assert!(ctx.thrown_value(&native_err).is_none());
+    }
+
+    #[test]
+    fn parses_json() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx
+            .parse_json(r#"{"name": "esperanto", "tags": ["one", "two"]}"#)
+            .unwrap();
+        let name: String = value.get_property("name").unwrap().try_convert().unwrap();
+        assert_eq!(name, "esperanto");
+
+        let tags: Vec<String> = value.get_property("tags").unwrap().try_convert().unwrap();
+        assert_eq!(tags, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn throws_syntax_error_on_invalid_json() {
+        let ctx = JSContext::new().unwrap();
+        let result = ctx.parse_json("{'single': 'quotes'}").unwrap_err();
+        match result {
+            EsperantoError::JavaScriptError(err) => {
+                assert_eq!(err.name, "SyntaxError");
+            }
+            _ => panic!("Unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn throws_syntax_error_on_json_containing_nul() {
+        let ctx = JSContext::new().unwrap();
+        for json in &["\"a\0b\"", "1\0", "[1, \0 2]"] {
+            match ctx.parse_json(json).unwrap_err() {
+                EsperantoError::JavaScriptError(err) => {
+                    assert_eq!(err.name, "SyntaxError", "{:?}", json);
+                    assert!(!err.message.is_empty());
+                }
+                err => panic!("Unexpected error type {:?} for {:?}", err, json),
+            }
+        }
+
+        let value = ctx.parse_json("\"a\\u0000b\"").unwrap();
+        assert_eq!(value.try_convert::<String>().unwrap(), "a\0b");
+    }
 }
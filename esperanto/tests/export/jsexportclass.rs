@@ -1,9 +1,12 @@
 #[cfg(test)]
 mod test {
     use esperanto::errors::{JSExportError, JavaScriptError};
-    use esperanto::export::{JSClassFunction, Js};
-    use esperanto::{EsperantoError, JSValue};
+    use esperanto::export::{
+        JSClassFunction, JSClassMethod, JSExportAttribute, JSExportParent, Js,
+    };
+    use esperanto::{EsperantoError, FromJSArgs, JSValue, WellKnownSymbol};
     use esperanto::{JSContext, JSExportClass};
+    use phf::phf_ordered_map;
 
     #[test]
     fn exports_sets_prototype() {
@@ -151,6 +154,78 @@ mod test {
         assert_eq!(as_ref.value_two, "test");
     }
 
+    #[test]
+    fn constructor_arguments_can_be_extracted() {
+        struct TestStruct {
+            value_one: f64,
+            value_two: Option<String>,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const CALL_AS_CONSTRUCTOR: Option<JSClassFunction> = Some(JSClassFunction {
+                num_args: 2,
+                func: |args, ctx| {
+                    let (value_one, value_two) = FromJSArgs::from_js_args(args, ctx)?;
+                    let item = TestStruct {
+                        value_one,
+                        value_two,
+                    };
+                    return JSValue::new_wrapped_native(item, ctx);
+                },
+            });
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let constructor = JSValue::constructor_for::<TestStruct>(&ctx).unwrap();
+        ctx.global_object()
+            .set_property("TestStruct", &constructor)
+            .unwrap();
+
+        let result = ctx.evaluate("new TestStruct(123, 'test')", None).unwrap();
+        let as_ref: Js<TestStruct> = result.as_native().unwrap();
+        assert_eq!(as_ref.value_one, 123.0);
+        assert_eq!(as_ref.value_two, Some("test".to_string()));
+
+        let result = ctx.evaluate("new TestStruct(456)", None).unwrap();
+        let as_ref: Js<TestStruct> = result.as_native().unwrap();
+        assert_eq!(as_ref.value_one, 456.0);
+        assert_eq!(as_ref.value_two, None);
+    }
+
+    #[test]
+    fn throws_type_error_when_argument_conversion_fails() {
+        struct TestStruct {}
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const CALL_AS_CONSTRUCTOR: Option<JSClassFunction> = Some(JSClassFunction {
+                num_args: 2,
+                func: |args, ctx| {
+                    let (_, _): (f64, JavaScriptError) = FromJSArgs::from_js_args(args, ctx)?;
+                    return JSValue::new_wrapped_native(TestStruct {}, ctx);
+                },
+            });
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let constructor = JSValue::constructor_for::<TestStruct>(&ctx).unwrap();
+        ctx.global_object()
+            .set_property("TestStruct", &constructor)
+            .unwrap();
+
+        let result = ctx.evaluate("new TestStruct(1, 'not an error')", None);
+        match result {
+            Err(EsperantoError::JavaScriptError(err)) => {
+                assert_eq!(err.name, "TypeError");
+                assert!(err
+                    .message
+                    .starts_with("Argument 1 could not be converted to JavaScriptError"));
+            }
+            _ => panic!("Expected a TypeError"),
+        }
+    }
+
     #[test]
     fn throws_error_when_constructor_fails() {
         struct TestStruct {}
@@ -452,32 +527,728 @@ mod test {
         unsafe { assert_eq!(IS_DESTROYED, true) };
     }
 
-    // #[ignore]
-    // #[test]
-    // fn export_attribute_property_getters_work() {
-    //     struct TestStruct {}
-
-    //     impl JSExportClass for TestStruct {
-    //         const CLASS_NAME: &'static str = "TestStruct";
-    //         const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
-    //             "testAttribute" => JSExportAttribute::Property {
-    //                 getter: | ctx, this_obj | {
-    //                     JSValue::try_new_from(123.0, &ctx)
-    //                 },
-    //                 setter: None
-    //             }
-    //         ));
-    //     }
-
-    //     let ctx = JSContext::new().unwrap();
-    //     let wrapped = JSValue::constructor_for::<TestStruct>(&ctx).unwrap();
-    //     ctx.global_object()
-    //         .set_property("TestValue", &wrapped)
-    //         .unwrap();
-
-    //     let result = ctx.evaluate("TestValue.testAttribute", None).unwrap();
-    //     assert!(JSValue::undefined(&ctx).value() != result.value());
-    //     let number: f64 = result.try_convert().unwrap();
-    //     assert_eq!(number, 123.0);
-    // }
+    #[test]
+    fn export_attribute_methods_work() {
+        struct TestStruct {
+            value: f64,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "addToValue" => JSExportAttribute::Function(JSClassMethod {
+                    num_args: 1,
+                    func: |ctx, this_obj, args| {
+                        let native = this_obj.as_native::<TestStruct>()?;
+                        let to_add: f64 = args[0].try_convert()?;
+                        JSValue::try_new_from(native.value + to_add, ctx)
+                    }
+                })
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let wrapped = JSValue::new_wrapped_native(TestStruct { value: 10.0 }, &ctx).unwrap();
+        ctx.global_object()
+            .set_property("testValue", &wrapped)
+            .unwrap();
+
+        let result: f64 = ctx
+            .evaluate("testValue.addToValue(5)", None)
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(result, 15.0);
+
+        let length: f64 = ctx
+            .evaluate("testValue.addToValue.length", None)
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(length, 1.0);
+
+        let name: String = ctx
+            .evaluate("testValue.addToValue.name", None)
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(name, "addToValue");
+
+        // methods aren't enumerable, same as in a JS class
+        let keys: String = ctx
+            .evaluate(
+                "Object.keys(Object.getPrototypeOf(testValue)).join(',')",
+                None,
+            )
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(keys, "");
+    }
+
+    #[test]
+    fn export_attribute_methods_throw_with_wrong_this() {
+        struct TestStruct {}
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "test" => JSExportAttribute::Function(JSClassMethod {
+                    num_args: 0,
+                    func: |ctx, this_obj, _| {
+                        this_obj.as_native::<TestStruct>()?;
+                        Ok(JSValue::undefined(ctx))
+                    }
+                })
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let wrapped = JSValue::new_wrapped_native(TestStruct {}, &ctx).unwrap();
+        ctx.global_object()
+            .set_property("testValue", &wrapped)
+            .unwrap();
+
+        ctx.evaluate("testValue.test()", None).unwrap();
+        let result = ctx.evaluate("testValue.test.call({})", None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn export_attribute_property_getters_work() {
+        struct TestStruct {}
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "testAttribute" => JSExportAttribute::Property {
+                    getter: |ctx, _this_obj| {
+                        JSValue::try_new_from(123.0, &ctx)
+                    },
+                    setter: None
+                }
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let wrapped = JSValue::new_wrapped_native(TestStruct {}, &ctx).unwrap();
+        ctx.global_object()
+            .set_property("testValue", &wrapped)
+            .unwrap();
+
+        let result = ctx.evaluate("testValue.testAttribute", None).unwrap();
+        let number: f64 = result.try_convert().unwrap();
+        assert_eq!(number, 123.0);
+    }
+
+    #[test]
+    fn export_attribute_property_setters_work() {
+        struct TestStruct {
+            value: std::cell::Cell<f64>,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "value" => JSExportAttribute::Property {
+                    getter: |ctx, this_obj| {
+                        let native = this_obj.as_native::<TestStruct>()?;
+                        JSValue::try_new_from(native.value.get(), ctx)
+                    },
+                    setter: Some(|_, this_obj, new_value| {
+                        let native = this_obj.as_native::<TestStruct>()?;
+                        native.value.set(new_value.try_convert()?);
+                        Ok(())
+                    })
+                }
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let wrapped = JSValue::new_wrapped_native(
+            TestStruct {
+                value: std::cell::Cell::new(1.0),
+            },
+            &ctx,
+        )
+        .unwrap();
+        ctx.global_object()
+            .set_property("testValue", &wrapped)
+            .unwrap();
+
+        let result: f64 = ctx
+            .evaluate("testValue.value = 5; testValue.value", None)
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(result, 5.0);
+        assert_eq!(wrapped.as_native::<TestStruct>().unwrap().value.get(), 5.0);
+    }
+
+    #[test]
+    fn export_attribute_property_without_setter_is_read_only() {
+        struct TestStruct {}
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "testAttribute" => JSExportAttribute::Property {
+                    getter: |ctx, _| JSValue::try_new_from(123.0, ctx),
+                    setter: None
+                }
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let wrapped = JSValue::new_wrapped_native(TestStruct {}, &ctx).unwrap();
+        ctx.global_object()
+            .set_property("testValue", &wrapped)
+            .unwrap();
+
+        // Assignment is silently ignored outside of strict mode...
+        let result: f64 = ctx
+            .evaluate("testValue.testAttribute = 5; testValue.testAttribute", None)
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(result, 123.0);
+
+        // ...but throws inside it
+        let err = ctx
+            .evaluate("'use strict'; testValue.testAttribute = 5", None)
+            .unwrap_err();
+        match err {
+            EsperantoError::JavaScriptError(err) => assert_eq!(err.name, "TypeError"),
+            _ => panic!("Unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn export_static_attributes_are_on_constructor() {
+        struct TestStruct {
+            value: f64,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const STATIC_ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "VERSION" => JSExportAttribute::Property {
+                    getter: |ctx, _| JSValue::try_new_from(2.0, ctx),
+                    setter: None
+                },
+                "withValue" => JSExportAttribute::Function(JSClassMethod {
+                    num_args: 1,
+                    func: |ctx, _, args| {
+                        let value: f64 = args[0].try_convert()?;
+                        JSValue::new_wrapped_native(TestStruct { value }, ctx)
+                    }
+                })
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let constructor = JSValue::constructor_for::<TestStruct>(&ctx).unwrap();
+        ctx.global_object()
+            .set_property("TestStruct", &constructor)
+            .unwrap();
+
+        let version: f64 = ctx
+            .evaluate("TestStruct.VERSION", None)
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(version, 2.0);
+
+        let result = ctx.evaluate("TestStruct.withValue(5)", None).unwrap();
+        assert_eq!(result.as_native::<TestStruct>().unwrap().value, 5.0);
+
+        // statics shouldn't leak onto instances
+        let is_undefined: bool = ctx
+            .evaluate("TestStruct.withValue(5).VERSION === undefined", None)
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(is_undefined, true);
+    }
+
+    #[test]
+    fn export_constants_are_read_only_data_properties() {
+        struct TestStruct {}
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const CALL_AS_CONSTRUCTOR: Option<JSClassFunction> = Some(JSClassFunction {
+                num_args: 0,
+                func: |_, ctx| JSValue::new_wrapped_native(TestStruct {}, ctx),
+            });
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "kind" => JSExportAttribute::Constant(|ctx| JSValue::try_new_from("test", ctx))
+            ));
+            const STATIC_ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "VERSION" => JSExportAttribute::Constant(|ctx| JSValue::try_new_from(2.0, ctx)),
+                "DEFAULTS" => JSExportAttribute::Constant(|ctx| ctx.evaluate("({a: 1})", None))
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let constructor = JSValue::constructor_for::<TestStruct>(&ctx).unwrap();
+        ctx.global_object()
+            .set_property("TestStruct", &constructor)
+            .unwrap();
+
+        let check = |script: &str| {
+            let result: bool = ctx.evaluate(script, None).unwrap().try_convert().unwrap();
+            assert!(result, "{}", script);
+        };
+
+        check("TestStruct.VERSION === 2");
+        check("new TestStruct().kind === 'test'");
+        check("TestStruct.prototype.hasOwnProperty('kind')");
+
+        // A data property rather than an accessor, created just the once
+        check(
+            "const descriptor = Object.getOwnPropertyDescriptor(TestStruct, 'VERSION'); \
+             descriptor.value === 2 && !descriptor.writable && !descriptor.enumerable && \
+             !descriptor.configurable && descriptor.get === undefined",
+        );
+        check("TestStruct.DEFAULTS === TestStruct.DEFAULTS && TestStruct.DEFAULTS.a === 1");
+
+        assert!(ctx
+            .evaluate("'use strict'; TestStruct.VERSION = 3", None)
+            .is_err());
+        assert!(ctx
+            .evaluate("'use strict'; delete TestStruct.VERSION", None)
+            .is_err());
+        check("TestStruct.VERSION === 2 && Object.keys(TestStruct).length === 0");
+    }
+
+    #[test]
+    fn export_constructor_is_linked_to_prototype() {
+        struct TestStruct {}
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const CALL_AS_CONSTRUCTOR: Option<JSClassFunction> = Some(JSClassFunction {
+                num_args: 0,
+                func: |_, ctx| JSValue::new_wrapped_native(TestStruct {}, ctx),
+            });
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let constructor = JSValue::constructor_for::<TestStruct>(&ctx).unwrap();
+        let prototype = JSValue::prototype_for::<TestStruct>(&ctx).unwrap();
+        assert_eq!(constructor.get_property("prototype").unwrap(), prototype);
+
+        ctx.global_object()
+            .set_property("TestStruct", &constructor)
+            .unwrap();
+
+        let script = "
+            const instance = new TestStruct();
+            instance instanceof TestStruct && instance.constructor === TestStruct && TestStruct.name === 'TestStruct'
+        ";
+        let result: bool = ctx.evaluate(script, None).unwrap().try_convert().unwrap();
+        assert_eq!(result, true);
+    }
+
+    #[test]
+    fn native_objects_can_be_mutated() {
+        struct TestStruct {
+            count: u32,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "increment" => JSExportAttribute::Function(JSClassMethod {
+                    num_args: 0,
+                    func: |ctx, this_obj, _| {
+                        this_obj.as_native_mut::<TestStruct>()?.count += 1;
+                        Ok(JSValue::undefined(ctx))
+                    }
+                })
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let wrapped = JSValue::new_wrapped_native(TestStruct { count: 0 }, &ctx).unwrap();
+        ctx.global_object()
+            .set_property("testValue", &wrapped)
+            .unwrap();
+
+        ctx.evaluate("testValue.increment(); testValue.increment()", None)
+            .unwrap();
+        assert_eq!(wrapped.as_native::<TestStruct>().unwrap().count, 2);
+    }
+
+    #[test]
+    fn native_objects_cannot_be_borrowed_twice() {
+        #[derive(Debug)]
+        struct TestStruct {
+            count: u32,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "incrementThen" => JSExportAttribute::Function(JSClassMethod {
+                    num_args: 1,
+                    func: |ctx, this_obj, args| {
+                        let mut native = this_obj.as_native_mut::<TestStruct>()?;
+                        native.count += 1;
+                        args[0].call_as_function(vec![])?;
+                        Ok(JSValue::undefined(ctx))
+                    }
+                })
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let wrapped = JSValue::new_wrapped_native(TestStruct { count: 0 }, &ctx).unwrap();
+
+        {
+            let _borrowed = wrapped.as_native_mut::<TestStruct>().unwrap();
+            let err = wrapped.as_native::<TestStruct>().unwrap_err();
+            assert_eq!(
+                err,
+                EsperantoError::ExportError(JSExportError::NativeObjectAlreadyBorrowed(
+                    "TestStruct"
+                ))
+            );
+        }
+
+        ctx.global_object()
+            .set_property("testValue", &wrapped)
+            .unwrap();
+
+        // The re-entrant call fails rather than aliasing the first mutable borrow
+        let result = ctx.evaluate(
+            "testValue.incrementThen(() => testValue.incrementThen(() => {}))",
+            None,
+        );
+        assert!(result.is_err());
+        assert_eq!(wrapped.as_native::<TestStruct>().unwrap().count, 1);
+    }
+
+    #[derive(Debug)]
+    struct Event {
+        event_type: String,
+    }
+
+    impl JSExportClass for Event {
+        const CLASS_NAME: &'static str = "Event";
+        const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+            "getType" => JSExportAttribute::Function(JSClassMethod {
+                num_args: 0,
+                func: |ctx, this_obj, _| {
+                    let event = this_obj.as_native::<Event>()?;
+                    JSValue::try_new_from(event.event_type.as_str(), ctx)
+                }
+            })
+        ));
+    }
+
+    #[derive(Debug)]
+    struct MessageEvent {
+        event: Event,
+        data: String,
+    }
+
+    impl AsRef<Event> for MessageEvent {
+        fn as_ref(&self) -> &Event {
+            &self.event
+        }
+    }
+
+    impl AsMut<Event> for MessageEvent {
+        fn as_mut(&mut self) -> &mut Event {
+            &mut self.event
+        }
+    }
+
+    impl JSExportClass for MessageEvent {
+        const CLASS_NAME: &'static str = "MessageEvent";
+        const PARENT: Option<JSExportParent> = Some(JSExportParent::of::<Event, Self>());
+    }
+
+    fn new_message_event<'r, 'c>(ctx: &'c JSContext<'r, 'c>) -> esperanto::Retain<JSValue<'r, 'c>> {
+        let event = MessageEvent {
+            event: Event {
+                event_type: "message".to_string(),
+            },
+            data: "hello".to_string(),
+        };
+        JSValue::new_wrapped_native(event, ctx).unwrap()
+    }
+
+    #[test]
+    fn export_subclass_is_instance_of_parent() {
+        let ctx = JSContext::new().unwrap();
+        let wrapped = new_message_event(&ctx);
+        let event_constructor = JSValue::constructor_for::<Event>(&ctx).unwrap();
+        let message_event_constructor = JSValue::constructor_for::<MessageEvent>(&ctx).unwrap();
+
+        assert_eq!(
+            wrapped.is_instance_of(&message_event_constructor).unwrap(),
+            true
+        );
+        assert_eq!(wrapped.is_instance_of(&event_constructor).unwrap(), true);
+
+        ctx.global_object()
+            .set_property("Event", &event_constructor)
+            .unwrap();
+        ctx.global_object()
+            .set_property("MessageEvent", &message_event_constructor)
+            .unwrap();
+        let result = ctx
+            .evaluate("Object.getPrototypeOf(MessageEvent) === Event", None)
+            .unwrap();
+        assert_eq!(result.try_convert::<bool>().unwrap(), true);
+    }
+
+    #[test]
+    fn export_subclass_inherits_parent_attributes() {
+        let ctx = JSContext::new().unwrap();
+        let wrapped = new_message_event(&ctx);
+        ctx.global_object()
+            .set_property("testValue", &wrapped)
+            .unwrap();
+
+        let result = ctx.evaluate("testValue.getType()", None).unwrap();
+        assert_eq!(result.try_convert::<String>().unwrap(), "message");
+    }
+
+    #[test]
+    fn export_subclass_can_be_used_as_parent() {
+        let ctx = JSContext::new().unwrap();
+        let wrapped = new_message_event(&ctx);
+
+        assert_eq!(wrapped.as_native::<Event>().unwrap().event_type, "message");
+        assert_eq!(wrapped.as_native::<MessageEvent>().unwrap().data, "hello");
+
+        wrapped.as_native_mut::<Event>().unwrap().event_type = "changed".to_string();
+        assert_eq!(
+            wrapped
+                .as_native::<MessageEvent>()
+                .unwrap()
+                .event
+                .event_type,
+            "changed"
+        );
+
+        // ...but not the other way around
+        let event = Event {
+            event_type: "test".to_string(),
+        };
+        let wrapped_parent = JSValue::new_wrapped_native(event, &ctx).unwrap();
+        assert_eq!(
+            wrapped_parent.as_native::<MessageEvent>().unwrap_err(),
+            EsperantoError::ExportError(JSExportError::CouldNotGetNativeObject("MessageEvent"))
+        );
+    }
+
+    #[test]
+    fn js_classes_can_extend_exported_classes() {
+        struct TestStruct {
+            value: f64,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const CALL_AS_CONSTRUCTOR: Option<JSClassFunction> = Some(JSClassFunction {
+                num_args: 1,
+                func: |args, ctx| {
+                    let value: f64 = args[0].try_convert()?;
+                    JSValue::new_wrapped_native(TestStruct { value }, ctx)
+                },
+            });
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "getValue" => JSExportAttribute::Function(JSClassMethod {
+                    num_args: 0,
+                    func: |ctx, this_obj, _| {
+                        let value = this_obj.as_native::<TestStruct>()?.value;
+                        JSValue::try_new_from(value, ctx)
+                    }
+                })
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let constructor = JSValue::constructor_for::<TestStruct>(&ctx).unwrap();
+        ctx.global_object()
+            .set_property("TestStruct", &constructor)
+            .unwrap();
+
+        let instance = ctx
+            .evaluate(
+                "
+                class SubClass extends TestStruct {
+                    doubled() { return this.getValue() * 2 }
+                }
+                new SubClass(3)
+                ",
+                None,
+            )
+            .unwrap();
+        ctx.global_object()
+            .set_property("instance", &instance)
+            .unwrap();
+
+        let is_subclass = ctx.evaluate("instance instanceof SubClass", None).unwrap();
+        assert_eq!(is_subclass.try_convert::<bool>().unwrap(), true);
+        assert_eq!(instance.is_instance_of(&constructor).unwrap(), true);
+
+        let doubled = ctx.evaluate("instance.doubled()", None).unwrap();
+        assert_eq!(doubled.try_convert::<f64>().unwrap(), 6.0);
+        assert_eq!(instance.as_native::<TestStruct>().unwrap().value, 3.0);
+
+        // Constructing the class directly still uses its own prototype
+        let is_direct = ctx
+            .evaluate(
+                "Object.getPrototypeOf(new TestStruct(1)) === TestStruct.prototype",
+                None,
+            )
+            .unwrap();
+        assert_eq!(is_direct.try_convert::<bool>().unwrap(), true);
+
+        // Errors reading new.target.prototype are thrown rather than left pending
+        let err = ctx
+            .evaluate(
+                "
+                const target = new Proxy(function() {}, {
+                    get(target, key) {
+                        if (key === 'prototype') throw new Error('no prototype');
+                        return target[key];
+                    }
+                });
+                Reflect.construct(TestStruct, [1], target)
+                ",
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Error: no prototype");
+        let after = ctx.evaluate("new SubClass(2).doubled()", None).unwrap();
+        assert_eq!(after.try_convert::<f64>().unwrap(), 4.0);
+    }
+
+    #[test]
+    fn exported_classes_ignore_replaced_globals() {
+        struct TestStruct {
+            value: f64,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+            const CALL_AS_CONSTRUCTOR: Option<JSClassFunction> = Some(JSClassFunction {
+                num_args: 1,
+                func: |args, ctx| {
+                    let value: f64 = args[0].try_convert()?;
+                    JSValue::new_wrapped_native(TestStruct { value }, ctx)
+                },
+            });
+            const ATTRIBUTES: esperanto::export::JSExportAttributes = Some(phf_ordered_map!(
+                "value" => JSExportAttribute::Property {
+                    getter: |ctx, this_obj| {
+                        let value = this_obj.as_native::<TestStruct>()?.value;
+                        JSValue::try_new_from(value, ctx)
+                    },
+                    setter: None
+                }
+            ));
+        }
+
+        let ctx = JSContext::new().unwrap();
+        ctx.evaluate(
+            "
+            const hijacked = () => { throw new Error('hijacked') };
+            Object.defineProperty = hijacked;
+            Object.setPrototypeOf = hijacked;
+            Reflect.construct = hijacked;
+            globalThis.Proxy = hijacked;
+            globalThis.Function = hijacked;
+            Object.prototype.get = hijacked;
+            ",
+            None,
+        )
+        .unwrap();
+
+        let constructor = JSValue::constructor_for::<TestStruct>(&ctx).unwrap();
+        ctx.global_object()
+            .set_property("TestStruct", &constructor)
+            .unwrap();
+
+        let result = ctx
+            .evaluate(
+                "
+                class SubClass extends TestStruct {
+                    doubled() { return this.value * 2 }
+                }
+                const instance = new SubClass(3);
+                instance instanceof SubClass && instance.doubled() === 6 &&
+                    new TestStruct(1).value === 1 && typeof TestStruct.call === 'function'
+                ",
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.try_convert::<bool>().unwrap(), true);
+    }
+
+    #[test]
+    fn prototype_can_set_to_string_tag() {
+        struct TestStruct {}
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let prototype = JSValue::prototype_for::<TestStruct>(&ctx).unwrap();
+        let tag_symbol = JSValue::well_known_symbol(WellKnownSymbol::ToStringTag, &ctx).unwrap();
+        let tag = JSValue::try_new_from("TestStruct", &ctx).unwrap();
+        prototype.set_property(&tag_symbol, &tag).unwrap();
+
+        let wrapped = JSValue::new_wrapped_native(TestStruct {}, &ctx).unwrap();
+        ctx.global_object().set_property("wrapped", &wrapped).unwrap();
+        let result = ctx
+            .evaluate("Object.prototype.toString.call(wrapped)", None)
+            .unwrap();
+        assert_eq!(result.try_convert::<String>().unwrap(), "[object TestStruct]");
+    }
+
+    #[test]
+    fn prototype_can_be_made_iterable() {
+        struct TestStruct {
+            items: Vec<f64>,
+        }
+
+        impl JSExportClass for TestStruct {
+            const CLASS_NAME: &'static str = "TestStruct";
+        }
+
+        let ctx = JSContext::new().unwrap();
+        let prototype = JSValue::prototype_for::<TestStruct>(&ctx).unwrap();
+        let iterator_symbol = JSValue::well_known_symbol(WellKnownSymbol::Iterator, &ctx).unwrap();
+        let iterate = JSValue::new_native_function(
+            |ctx, this, _| {
+                let instance: Js<TestStruct> = this.as_native()?;
+                JSValue::new_iterator(instance.items.clone().into_iter(), ctx)
+            },
+            &ctx,
+        )
+        .unwrap();
+        prototype.set_property(&iterator_symbol, &iterate).unwrap();
+
+        let wrapped = JSValue::new_wrapped_native(
+            TestStruct {
+                items: vec![1.0, 2.0, 3.0],
+            },
+            &ctx,
+        )
+        .unwrap();
+        ctx.global_object().set_property("wrapped", &wrapped).unwrap();
+        let result = ctx
+            .evaluate("Math.max(...wrapped) + Array.from(wrapped).length", None)
+            .unwrap();
+        assert_eq!(result.try_convert::<f64>().unwrap(), 6.0);
+    }
 }
@@ -1,58 +1,270 @@
-// // use std::intrinsics::transmute;
+#[cfg(test)]
+mod test {
+    use esperanto::errors::JavaScriptError;
+    use esperanto::export::js_export;
+    use esperanto::{EsperantoError, EsperantoResult, JSContext, JSValue};
 
-// use std::slice::Iter;
+    struct Counter {
+        count: f64,
+    }
 
-// use esperanto::{js_export_class, js_export_function, JSValueRef};
+    #[js_export]
+    impl Counter {
+        #[js_constant(name = "VERSION")]
+        const VERSION: f64 = 2.0;
 
-// use javascriptcore_sys::{JSClassDefinition, JSGlobalContextCreate, OpaqueJSContext};
+        #[js_constant]
+        const KIND: &'static str = "counter";
 
-// // // fn wat<'a>() -> &'a String {
-// // //     let hm = "".to_string();
-// // //     &hm
-// // // }
+        #[js_constructor]
+        fn new(start: f64) -> Self {
+            Counter { count: start }
+        }
 
-// // // impl javascriptcore_sys::OpaqueJSContextGroup {}
+        #[js_function(name = "increment")]
+        fn increment(&mut self, by: f64) -> f64 {
+            self.count += by;
+            self.count
+        }
 
-// // trait Blerg {
-// //     fn new() -> String;
-// // }
+        #[js_function]
+        fn reset(&mut self) {
+            self.count = 0.0;
+        }
 
-// // struct What {
-// //     // ptr: *mut String,
-// // }
+        #[js_function(name = "failWith")]
+        fn fail_with(&self, message: String) -> EsperantoResult<f64> {
+            Err(JavaScriptError::new("TestError".to_string(), message).into())
+        }
 
-// // fn what() {
-// //     let test = vec![1, 2];
-// //     let wat = test.iter().map(|i| i * 2);
-// //     let h = wat.into_iter();
-// //     let hm = wat.collect();
-// // }
+        #[js_getter(name = "count")]
+        fn count(&self) -> f64 {
+            self.count
+        }
 
-// struct Thing {}
+        #[js_setter(name = "count")]
+        fn set_count(&mut self, value: f64) {
+            self.count = value;
+        }
 
-// impl Thing {
-//     js_export_function! {
-//         fn export_name<'c>(arr: Vec<JSValueRef<'c>>) -> JSValueRef<'c> {
-//             return arr[0];
-//         }
-//     }
-// }
+        #[js_getter(name = "isZero")]
+        fn is_zero(&self) -> bool {
+            self.count == 0.0
+        }
 
-// js_export_class!(Thing, "Thing", {
-//     call_as_function: Some(Thing::export_name)
-//     // static_functions: {
-//     //     "testFunction" : Thing::export_name
-//     // }
-// });
+        #[js_function(name = "describe")]
+        fn describe(name: String) -> String {
+            format!("Counter: {}", name)
+        }
 
-// // #[test]
-// // fn test() {
-// //     let one = "one".to_string();
-// //     let two = "two".to_string();
+        #[js_function(name = "add")]
+        fn add(&mut self, by: Option<f64>) -> f64 {
+            self.count += by.unwrap_or(1.0);
+            self.count
+        }
 
-// //     let result = take_strings(&one, &two);
+        #[js_function(name = "throwError")]
+        fn throw_error(&self, error: JavaScriptError) -> EsperantoResult<()> {
+            Err(error.into())
+        }
 
-// //     let s = result.str_one;
+        #[allow(dead_code)]
+        fn not_exported(&self) -> f64 {
+            self.count
+        }
+    }
 
-// //     unsafe { println!("{}", *s) };
-// // }
+    fn context_with_counter<'r, 'c>(ctx: &'c JSContext<'r, 'c>) {
+        let constructor = JSValue::constructor_for::<Counter>(ctx).unwrap();
+        ctx.global_object()
+            .set_property("Counter", &constructor)
+            .unwrap();
+    }
+
+    #[test]
+    fn macro_sets_class_name() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx.evaluate("Counter.name", None).unwrap();
+        assert_eq!(result.try_convert::<String>().unwrap(), "Counter");
+    }
+
+    #[test]
+    fn macro_creates_constructor() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx.evaluate("new Counter(5)", None).unwrap();
+        assert_eq!(result.as_native::<Counter>().unwrap().count, 5.0);
+    }
+
+    #[test]
+    fn only_instances_convert_to_native() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        for script in &[
+            "Counter.prototype",
+            "Counter.prototype.increment",
+            "Object.getOwnPropertyDescriptor(Counter.prototype, 'count').get",
+            "Counter.describe",
+            "Counter",
+        ] {
+            let value = ctx.evaluate(script, None).unwrap();
+            assert!(value.as_native::<Counter>().is_err(), "{}", script);
+        }
+    }
+
+    #[test]
+    fn macro_creates_methods() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx
+            .evaluate(
+                "const c = new Counter(1); c.increment(2); c.increment(3)",
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.try_convert::<f64>().unwrap(), 6.0);
+
+        let result = ctx.evaluate("c.reset()", None).unwrap();
+        assert_eq!(*result, *JSValue::undefined(&ctx));
+
+        let result = ctx.evaluate("c.count", None).unwrap();
+        assert_eq!(result.try_convert::<f64>().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn macro_creates_real_functions() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx
+            .evaluate(
+                "const c = new Counter(1); \
+                const count = Object.getOwnPropertyDescriptor(Counter.prototype, 'count'); \
+                typeof c.increment === 'function' && \
+                c.increment.call(c, 2) === 3 && \
+                c.increment.bind(c)(3) === 6 && \
+                count.get.call(c) === 6 && \
+                Counter.describe.apply(null, ['test']) === 'Counter: test'",
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.try_convert::<bool>().unwrap(), true);
+    }
+
+    #[test]
+    fn macro_does_not_export_unmarked_functions() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx
+            .evaluate("typeof new Counter(1).not_exported", None)
+            .unwrap();
+        assert_eq!(result.try_convert::<String>().unwrap(), "undefined");
+    }
+
+    #[test]
+    fn macro_creates_properties() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx
+            .evaluate("const c = new Counter(1); c.count = 10; c.count", None)
+            .unwrap();
+        assert_eq!(result.try_convert::<f64>().unwrap(), 10.0);
+
+        let result = ctx.evaluate("c.isZero", None).unwrap();
+        assert_eq!(result.try_convert::<bool>().unwrap(), false);
+
+        // No setter means read-only
+        let result = ctx.evaluate("'use strict'; c.isZero = true", None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn macro_creates_static_methods() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx.evaluate("Counter.describe('test')", None).unwrap();
+        assert_eq!(result.try_convert::<String>().unwrap(), "Counter: test");
+    }
+
+    #[test]
+    fn macro_creates_static_constants() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx
+            .evaluate("Counter.VERSION === 2 && Counter.KIND === 'counter'", None)
+            .unwrap();
+        assert_eq!(result.try_convert::<bool>().unwrap(), true);
+
+        let result = ctx.evaluate("'use strict'; Counter.VERSION = 3", None);
+        assert!(result.is_err());
+        let result = ctx.evaluate("new Counter(0).VERSION === undefined", None);
+        assert_eq!(result.unwrap().try_convert::<bool>().unwrap(), true);
+    }
+
+    #[test]
+    fn macro_throws_returned_errors() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx.evaluate("new Counter(1).failWith('oh no')", None);
+        match result {
+            Err(EsperantoError::JavaScriptError(err)) => {
+                assert_eq!(err.name, "TestError");
+                assert_eq!(err.message, "oh no");
+            }
+            _ => panic!("Expected a JavaScriptError"),
+        }
+    }
+
+    #[test]
+    fn macro_allows_optional_arguments() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx
+            .evaluate("const c = new Counter(1); c.add(); c.add(5)", None)
+            .unwrap();
+        assert_eq!(result.try_convert::<f64>().unwrap(), 7.0);
+    }
+
+    #[test]
+    fn macro_throws_type_error_for_bad_arguments() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx.evaluate("new Counter(1).throwError('not an error')", None);
+        match result {
+            Err(EsperantoError::JavaScriptError(err)) => {
+                assert_eq!(err.name, "TypeError");
+                assert!(err.message.starts_with("Argument 0"));
+            }
+            _ => panic!("Expected a TypeError"),
+        }
+    }
+
+    #[test]
+    fn macro_throws_type_error_for_bad_property_values() {
+        let ctx = JSContext::new().unwrap();
+        context_with_counter(&ctx);
+        let result = ctx.evaluate("new Counter(1).count = Symbol()", None);
+        match result {
+            Err(EsperantoError::JavaScriptError(err)) => {
+                assert_eq!(err.name, "TypeError");
+                assert!(err
+                    .message
+                    .starts_with("Argument 0 could not be converted to f64"));
+            }
+            _ => panic!("Expected a TypeError"),
+        }
+    }
+
+    #[test]
+    fn macro_uses_custom_class_name() {
+        struct Renamed {}
+
+        #[js_export(name = "SomethingElse")]
+        impl Renamed {}
+
+        let ctx = JSContext::new().unwrap();
+        let constructor = JSValue::constructor_for::<Renamed>(&ctx).unwrap();
+        let name = constructor.get_property("name").unwrap();
+        assert_eq!(name.try_convert::<String>().unwrap(), "SomethingElse");
+    }
+}
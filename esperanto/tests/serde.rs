@@ -0,0 +1,164 @@
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+
+    use std::collections::HashMap;
+
+    use esperanto::serde::{from_value, to_value};
+    use esperanto::{JSContext, JSValue, TryConvertJSValue};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Person {
+        name: String,
+        age: u8,
+        nickname: Option<String>,
+        scores: Vec<f64>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Point(i32, i32),
+        Rect { width: u32, height: u32 },
+    }
+
+    #[test]
+    fn serializes_structs_to_objects() {
+        let ctx = JSContext::new().unwrap();
+        let person = Person {
+            name: "Alastair".to_string(),
+            age: 38,
+            nickname: None,
+            scores: vec![1.5, 2.0],
+        };
+        let value = to_value(&person, &ctx).unwrap();
+        ctx.global_object().set_property("person", &value).unwrap();
+
+        let check = ctx
+            .evaluate(
+                "person.name === 'Alastair' && person.age === 38 && person.nickname === null \
+                 && Array.isArray(person.scores) && person.scores[0] === 1.5",
+                None,
+            )
+            .unwrap();
+        assert_eq!(bool::try_from_jsvalue(&check).unwrap(), true);
+    }
+
+    #[test]
+    fn deserializes_objects_to_structs() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx
+            .evaluate(
+                "({name: 'Alastair', age: 38, nickname: 'Al', scores: [3, 4.5]})",
+                None,
+            )
+            .unwrap();
+        let person: Person = from_value(&value).unwrap();
+        assert_eq!(
+            person,
+            Person {
+                name: "Alastair".to_string(),
+                age: 38,
+                nickname: Some("Al".to_string()),
+                scores: vec![3.0, 4.5],
+            }
+        );
+    }
+
+    #[test]
+    fn treats_missing_and_null_fields_as_none() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx
+            .evaluate("({name: 'A', age: 1, scores: []})", None)
+            .unwrap();
+        let person: Person = from_value(&value).unwrap();
+        assert_eq!(person.nickname, None);
+
+        let value = ctx.evaluate("null", None).unwrap();
+        let nothing: Option<f64> = from_value(&value).unwrap();
+        assert_eq!(nothing, None);
+    }
+
+    #[test]
+    fn round_trips_enums() {
+        let ctx = JSContext::new().unwrap();
+        let shapes = vec![
+            Shape::Empty,
+            Shape::Circle(2.5),
+            Shape::Point(-1, 3),
+            Shape::Rect {
+                width: 10,
+                height: 20,
+            },
+        ];
+        let value = to_value(&shapes, &ctx).unwrap();
+        ctx.global_object().set_property("shapes", &value).unwrap();
+
+        let check = ctx
+            .evaluate(
+                "shapes[0] === 'Empty' && shapes[1].Circle === 2.5 && shapes[2].Point[1] === 3 \
+                 && shapes[3].Rect.height === 20",
+                None,
+            )
+            .unwrap();
+        assert_eq!(bool::try_from_jsvalue(&check).unwrap(), true);
+
+        let round_tripped: Vec<Shape> = from_value(&value).unwrap();
+        assert_eq!(round_tripped, shapes);
+    }
+
+    #[test]
+    fn round_trips_maps() {
+        let ctx = JSContext::new().unwrap();
+        let mut map = HashMap::new();
+        map.insert("one".to_string(), 1);
+        map.insert("two".to_string(), 2);
+
+        let value = to_value(&map, &ctx).unwrap();
+        let round_tripped: HashMap<String, i32> = from_value(&value).unwrap();
+        assert_eq!(round_tripped, map);
+    }
+
+    #[test]
+    fn round_trips_maps_with_nul_in_keys() {
+        let ctx = JSContext::new().unwrap();
+        let mut map = HashMap::new();
+        map.insert("a\0b".to_string(), 1);
+        map.insert("a".to_string(), 2);
+
+        let value = to_value(&map, &ctx).unwrap();
+        let round_tripped: HashMap<String, i32> = from_value(&value).unwrap();
+        assert_eq!(round_tripped, map);
+    }
+
+    #[test]
+    fn serializes_large_integers_as_bigints() {
+        let ctx = JSContext::new().unwrap();
+        let value = to_value(&u64::MAX, &ctx).unwrap();
+        ctx.global_object().set_property("big", &value).unwrap();
+
+        let check = ctx
+            .evaluate(
+                "typeof big === 'bigint' && big === 18446744073709551615n",
+                None,
+            )
+            .unwrap();
+        assert_eq!(bool::try_from_jsvalue(&check).unwrap(), true);
+
+        let round_tripped: u64 = from_value(&value).unwrap();
+        assert_eq!(round_tripped, u64::MAX);
+    }
+
+    #[test]
+    fn fails_to_deserialize_mismatched_types() {
+        let ctx = JSContext::new().unwrap();
+        let value = JSValue::try_new_from("not a number", &ctx).unwrap();
+        let result: Result<f64, _> = from_value(&value);
+        assert!(result.is_err());
+
+        let value = JSValue::try_new_from(1.5, &ctx).unwrap();
+        let result: Result<u8, _> = from_value(&value);
+        assert!(result.is_err());
+    }
+}
@@ -2,7 +2,10 @@
 mod value_tests {
 
     use esperanto::errors::JSValueError;
-    use esperanto::{EsperantoError, JSContext, JSRuntime, JSValue, TryConvertJSValue};
+    use esperanto::{
+        EsperantoError, JSContext, JSRuntime, JSValue, JSValueType, TryConvertJSValue,
+        WellKnownSymbol,
+    };
 
     #[test]
     fn sets_properties() {
@@ -70,6 +73,109 @@ mod value_tests {
         );
     }
 
+    #[test]
+    fn lists_own_property_names() {
+        let ctx = JSContext::new().unwrap();
+        let obj = ctx
+            .evaluate(
+                "const obj = {first: 1, second: 2}; Object.defineProperty(obj, 'hidden', {value: 3}); obj",
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(obj.own_property_names().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn lists_property_names_containing_nul() {
+        let ctx = JSContext::new().unwrap();
+        let obj = ctx.evaluate("({'a\\u0000b': 1, c: 2})", None).unwrap();
+        assert_eq!(obj.own_property_names().unwrap(), vec!["a\0b", "c"]);
+    }
+
+    #[test]
+    fn does_not_list_inherited_property_names() {
+        let ctx = JSContext::new().unwrap();
+        let obj = ctx
+            .evaluate(
+                "const obj = Object.create({inherited: 1}); obj.own = 2; obj",
+                None,
+            )
+            .unwrap();
+        assert_eq!(obj.own_property_names().unwrap(), vec!["own"]);
+    }
+
+    #[test]
+    fn lists_property_names_when_globals_are_replaced() {
+        let ctx = JSContext::new().unwrap();
+        let obj = ctx
+            .evaluate(
+                "Object.keys = () => []; \
+                 Object.prototype.hasOwnProperty = () => true; \
+                 const obj = Object.create({inherited: 1}); obj.own = 2; obj",
+                None,
+            )
+            .unwrap();
+        assert_eq!(obj.own_property_names().unwrap(), vec!["own"]);
+    }
+
+    #[test]
+    fn creates_objects() {
+        let ctx = JSContext::new().unwrap();
+        let obj = JSValue::new_object(&ctx).unwrap();
+        assert_eq!(obj.own_property_names().unwrap().len(), 0);
+
+        let value = JSValue::try_new_from(1, &ctx).unwrap();
+        obj.set_property("added", &value).unwrap();
+        assert_eq!(obj.own_property_names().unwrap(), vec!["added"]);
+    }
+
+    #[test]
+    fn creates_arrays() {
+        let ctx = JSContext::new().unwrap();
+        let one = JSValue::try_new_from(1, &ctx).unwrap();
+        let two = JSValue::try_new_from("two", &ctx).unwrap();
+
+        let array = JSValue::new_array(vec![&one, &two], &ctx).unwrap();
+        ctx.global_object().set_property("arr", &array).unwrap();
+
+        let result = ctx
+            .evaluate(
+                "Array.isArray(arr) && arr[0] === 1 && arr[1] === 'two'",
+                None,
+            )
+            .unwrap();
+        assert_eq!(bool::try_from_jsvalue(&result).unwrap(), true);
+        assert_eq!(array.is_array().unwrap(), true);
+        assert_eq!(array.array_length().unwrap(), 2);
+    }
+
+    #[test]
+    fn gets_and_sets_array_indexes() {
+        let ctx = JSContext::new().unwrap();
+        let array = ctx.evaluate("[10, 20, 30]", None).unwrap();
+
+        let value = array.get_property_at_index(1).unwrap();
+        assert_eq!(i32::try_from_jsvalue(&value).unwrap(), 20);
+
+        let new_value = JSValue::try_new_from(40, &ctx).unwrap();
+        array.set_property_at_index(3, &new_value).unwrap();
+        assert_eq!(array.array_length().unwrap(), 4);
+
+        let value = array.get_property_at_index(3).unwrap();
+        assert_eq!(i32::try_from_jsvalue(&value).unwrap(), 40);
+    }
+
+    #[test]
+    fn errors_when_getting_length_of_non_array() {
+        let ctx = JSContext::new().unwrap();
+        let obj = ctx.evaluate("({length: 3})", None).unwrap();
+        assert_eq!(obj.is_array().unwrap(), false);
+
+        let err = obj.array_length().unwrap_err();
+        assert_eq!(err, JSValueError::IsNotAnArray.into());
+    }
+
     #[test]
     fn calls_function() {
         let ctx = JSContext::new().unwrap();
@@ -131,6 +237,98 @@ mod value_tests {
         );
     }
 
+    #[test]
+    fn creates_native_function() {
+        let ctx = JSContext::new().unwrap();
+        let prefix = "Hello".to_string();
+        let func = JSValue::new_native_function(
+            move |ctx, this_obj, args| {
+                let name: String = this_obj.get_property("name")?.try_convert()?;
+                let greeting: String = args[0].try_convert()?;
+                JSValue::try_new_from(format!("{} {}, {}", prefix, name, greeting), ctx)
+            },
+            &ctx,
+        )
+        .unwrap();
+
+        ctx.global_object().set_property("greet", &func).unwrap();
+
+        let result: String = ctx
+            .evaluate("greet.call({name: 'World'}, 'how are you?')", None)
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(result, "Hello World, how are you?");
+    }
+
+    #[test]
+    fn native_function_errors_are_thrown() {
+        let ctx = JSContext::new().unwrap();
+        let func =
+            JSValue::new_native_function(|_, _, _| Err(JSValueError::IsNotAnObject.into()), &ctx)
+                .unwrap();
+
+        ctx.global_object().set_property("fails", &func).unwrap();
+        let result: bool = ctx
+            .evaluate(
+                "try { fails(); false } catch (e) { e instanceof Error }",
+                None,
+            )
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(result, true);
+    }
+
+    #[test]
+    fn native_functions_rethrow_js_values_unchanged() {
+        let ctx = JSContext::new().unwrap();
+        let func =
+            JSValue::new_native_function(|_, _, args| args[0].call_as_function(vec![]), &ctx)
+                .unwrap();
+
+        ctx.global_object()
+            .set_property("callThrough", &func)
+            .unwrap();
+        let result: bool = ctx
+            .evaluate(
+                "const payload = { code: 42 }; \
+                try { callThrough(() => { throw payload }); false } catch (e) { e === payload }",
+                None,
+            )
+            .unwrap()
+            .try_convert()
+            .unwrap();
+        assert_eq!(result, true);
+    }
+
+    #[test]
+    fn drops_native_function_state() {
+        struct DropFlag(std::rc::Rc<std::cell::Cell<bool>>);
+
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let dropped = std::rc::Rc::new(std::cell::Cell::new(false));
+        {
+            let ctx = JSContext::new().unwrap();
+            let flag = DropFlag(dropped.clone());
+            let _func = JSValue::new_native_function(
+                move |ctx, _, _| {
+                    let _ = &flag;
+                    Ok(JSValue::undefined(ctx))
+                },
+                &ctx,
+            )
+            .unwrap();
+            assert_eq!(dropped.get(), false);
+        }
+        assert_eq!(dropped.get(), true);
+    }
+
     #[test]
     fn calls_constructor() {
         let ctx = JSContext::new().unwrap();
@@ -222,4 +420,441 @@ mod value_tests {
             assert_eq!(hmm.to_string(), "TEST VALUE");
         }
     }
+
+    #[test]
+    fn converts_to_json_string() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx.evaluate("({one: 1, two: [true, null]})", None).unwrap();
+        assert_eq!(
+            value.to_json_string(0).unwrap().unwrap(),
+            r#"{"one":1,"two":[true,null]}"#
+        );
+
+        let value = ctx.evaluate("({one: 1})", None).unwrap();
+        assert_eq!(
+            value.to_json_string(2).unwrap().unwrap(),
+            "{\n  \"one\": 1\n}"
+        );
+    }
+
+    #[test]
+    fn returns_none_when_value_has_no_json_representation() {
+        let ctx = JSContext::new().unwrap();
+        let value = JSValue::undefined(&ctx);
+        assert_eq!(value.to_json_string(0).unwrap(), None);
+
+        let value = ctx.evaluate("(function() {})", None).unwrap();
+        assert_eq!(value.to_json_string(0).unwrap(), None);
+    }
+
+    #[test]
+    fn throws_type_error_when_converting_cycle_to_json() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx
+            .evaluate("var cycle = {}; cycle.self = cycle; cycle", None)
+            .unwrap();
+        match value.to_json_string(0).unwrap_err() {
+            EsperantoError::JavaScriptError(err) => {
+                assert_eq!(err.name, "TypeError");
+            }
+            _ => panic!("Unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn reports_value_types() {
+        let ctx = JSContext::new().unwrap();
+        let check_type = |script: &str, expected: JSValueType| {
+            let value = ctx.evaluate(script, None).unwrap();
+            assert_eq!(value.value_type(), expected, "{}", script);
+        };
+
+        check_type("undefined", JSValueType::Undefined);
+        check_type("null", JSValueType::Null);
+        check_type("true", JSValueType::Boolean);
+        check_type("1", JSValueType::Number);
+        check_type("1.5", JSValueType::Number);
+        check_type("10n", JSValueType::BigInt);
+        check_type("'hello'", JSValueType::String);
+        check_type("Symbol('test')", JSValueType::Symbol);
+        check_type("(function() {})", JSValueType::Function);
+        check_type("class Test {}; Test", JSValueType::Function);
+        check_type("({})", JSValueType::Object);
+        check_type("[1, 2]", JSValueType::Object);
+    }
+
+    #[test]
+    fn checks_value_types() {
+        let ctx = JSContext::new().unwrap();
+
+        let null = JSValue::null(&ctx);
+        assert!(null.is_null());
+        assert_eq!(null.is_undefined(), false);
+        assert_eq!(null.is_object(), false);
+
+        let undefined = JSValue::undefined(&ctx);
+        assert!(undefined.is_undefined());
+        assert_eq!(undefined.is_null(), false);
+
+        let func = ctx.evaluate("(function() {})", None).unwrap();
+        assert!(func.is_function());
+        assert!(func.is_object());
+
+        let obj = ctx.evaluate("({})", None).unwrap();
+        assert!(obj.is_object());
+        assert_eq!(obj.is_function(), false);
+
+        let num = JSValue::try_new_from(1.5, &ctx).unwrap();
+        assert!(num.is_number());
+        assert_eq!(num.is_bool(), false);
+
+        let boolean = JSValue::try_new_from(false, &ctx).unwrap();
+        assert!(boolean.is_bool());
+        assert_eq!(boolean.is_number(), false);
+
+        let symbol = ctx.evaluate("Symbol.iterator", None).unwrap();
+        assert!(symbol.is_symbol());
+        assert_eq!(symbol.is_bigint(), false);
+        assert_eq!(symbol.is_string(), false);
+    }
+
+    #[test]
+    fn reads_missing_properties_as_none() {
+        let ctx = JSContext::new().unwrap();
+        let obj = ctx
+            .evaluate("({present: 'here', empty: null})", None)
+            .unwrap();
+
+        let present: Option<String> = obj.get_property("present").unwrap().try_convert().unwrap();
+        assert_eq!(present, Some("here".to_string()));
+
+        let empty: Option<String> = obj.get_property("empty").unwrap().try_convert().unwrap();
+        assert_eq!(empty, None);
+
+        let missing: Option<String> = obj.get_property("missing").unwrap().try_convert().unwrap();
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn round_trips_strings_containing_nul() {
+        let ctx = JSContext::new().unwrap();
+        let value = JSValue::try_new_from("before\0after 😀", &ctx).unwrap();
+        ctx.global_object().set_property("str", &value).unwrap();
+
+        let length = ctx.evaluate("str.length", None).unwrap();
+        assert_eq!(length.try_convert::<f64>().unwrap(), 15.0);
+
+        let string: String = value.try_convert().unwrap();
+        assert_eq!(string, "before\0after 😀");
+    }
+
+    #[test]
+    fn converts_unpaired_surrogates() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx.evaluate("'a\\uD83D' + 'b'", None).unwrap();
+
+        assert!(value.try_convert::<String>().is_err());
+        assert_eq!(value.to_utf16().unwrap(), vec![0x61, 0xD83D, 0x62]);
+        assert_eq!(value.to_wtf8().unwrap(), vec![0x61, 0xED, 0xA0, 0xBD, 0x62]);
+        assert_eq!(value.to_string_lossy().unwrap(), "a\u{FFFD}b");
+    }
+
+    #[test]
+    fn returns_errors_when_string_conversion_throws() {
+        let ctx = JSContext::new().unwrap();
+        let value = ctx
+            .evaluate("({ toString() { throw new Error('nope') } })", None)
+            .unwrap();
+
+        match value.to_string_lossy().unwrap_err() {
+            EsperantoError::JavaScriptError(err) => assert_eq!(err.message, "nope"),
+            _ => panic!("Unexpected error type"),
+        }
+        assert!(value.to_wtf8().is_err());
+        assert!(value.to_utf16().is_err());
+
+        let symbol = JSValue::new_symbol(Some("test"), &ctx).unwrap();
+        assert!(symbol.to_string_lossy().is_err());
+    }
+
+    #[test]
+    fn creates_strings_from_utf16() {
+        let ctx = JSContext::new().unwrap();
+        let value = JSValue::try_new_from_utf16(&[0x61, 0x0, 0xDE00], &ctx).unwrap();
+        ctx.global_object().set_property("str", &value).unwrap();
+
+        let check = ctx
+            .evaluate(
+                "str.length === 3 && str.charCodeAt(1) === 0 && str.charCodeAt(2) === 0xDE00",
+                None,
+            )
+            .unwrap();
+        assert_eq!(check.try_convert::<bool>().unwrap(), true);
+        assert_eq!(value.to_utf16().unwrap(), vec![0x61, 0x0, 0xDE00]);
+    }
+
+    #[test]
+    fn uses_property_names_containing_nul() {
+        let ctx = JSContext::new().unwrap();
+        let obj = JSValue::new_object(&ctx).unwrap();
+        let value = JSValue::try_new_from(1.0, &ctx).unwrap();
+        obj.set_property("a\0b", &value).unwrap();
+        ctx.global_object().set_property("obj", &obj).unwrap();
+
+        let check = ctx
+            .evaluate("obj['a\\0b'] === 1 && obj.a === undefined", None)
+            .unwrap();
+        assert_eq!(check.try_convert::<bool>().unwrap(), true);
+        assert_eq!(obj.delete_property("a\0b").unwrap(), true);
+        assert!(obj.get_property("a\0b").unwrap().is_undefined());
+    }
+
+    #[test]
+    fn uses_index_property_keys() {
+        let ctx = JSContext::new().unwrap();
+        let array = ctx.evaluate("['zero', 'one']", None).unwrap();
+        let one: String = array.get_property(1).unwrap().try_convert().unwrap();
+        assert_eq!(one, "one");
+
+        let two = JSValue::try_new_from("two", &ctx).unwrap();
+        array.set_property(2, &two).unwrap();
+        assert_eq!(array.array_length().unwrap(), 3);
+
+        // Index keys and their string equivalents are the same property
+        let two: String = array.get_property("2").unwrap().try_convert().unwrap();
+        assert_eq!(two, "two");
+
+        assert_eq!(array.delete_property(0).unwrap(), true);
+        assert!(array.get_property(0).unwrap().is_undefined());
+    }
+
+    #[test]
+    fn uses_value_property_keys() {
+        let ctx = JSContext::new().unwrap();
+        let obj = ctx.evaluate("({})", None).unwrap();
+        let symbol = ctx.evaluate("Symbol('test')", None).unwrap();
+        let value = JSValue::try_new_from(1.0, &ctx).unwrap();
+
+        obj.set_property(&symbol, &value).unwrap();
+        assert_eq!(
+            obj.get_property(&symbol)
+                .unwrap()
+                .try_convert::<f64>()
+                .unwrap(),
+            1.0
+        );
+        // Symbol-keyed properties don't show up in the names list
+        assert_eq!(obj.own_property_names().unwrap().len(), 0);
+        assert_eq!(obj.delete_property(&symbol).unwrap(), true);
+        assert!(obj.get_property(&symbol).unwrap().is_undefined());
+
+        // Other values are converted to keys the same way JS would
+        let number_key = JSValue::try_new_from(3.0, &ctx).unwrap();
+        obj.set_property(&number_key, &value).unwrap();
+        assert_eq!(
+            obj.get_property("3").unwrap().try_convert::<f64>().unwrap(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn creates_unique_symbols() {
+        let ctx = JSContext::new().unwrap();
+        let one = JSValue::new_symbol(Some("test"), &ctx).unwrap();
+        let two = JSValue::new_symbol(Some("test"), &ctx).unwrap();
+        let anonymous = JSValue::new_symbol(None, &ctx).unwrap();
+
+        assert!(one.is_symbol());
+        assert_eq!(one.value_type(), JSValueType::Symbol);
+        assert_eq!(one, one.retain());
+        assert_ne!(one, two);
+
+        ctx.global_object().set_property("one", &one).unwrap();
+        ctx.global_object()
+            .set_property("anonymous", &anonymous)
+            .unwrap();
+        let check = ctx
+            .evaluate(
+                "one.description === 'test' && anonymous.description === undefined",
+                None,
+            )
+            .unwrap();
+        assert_eq!(check.try_convert::<bool>().unwrap(), true);
+    }
+
+    #[test]
+    fn gets_well_known_symbols() {
+        let ctx = JSContext::new().unwrap();
+        let iterator = JSValue::well_known_symbol(WellKnownSymbol::Iterator, &ctx).unwrap();
+        let async_iterator =
+            JSValue::well_known_symbol(WellKnownSymbol::AsyncIterator, &ctx).unwrap();
+
+        assert_eq!(iterator, ctx.evaluate("Symbol.iterator", None).unwrap());
+        assert_eq!(
+            async_iterator,
+            ctx.evaluate("Symbol.asyncIterator", None).unwrap()
+        );
+
+        let array = ctx.evaluate("[]", None).unwrap();
+        assert!(array.get_property(&iterator).unwrap().is_function());
+    }
+
+    #[test]
+    fn symbols_ignore_replaced_globals() {
+        let ctx = JSContext::new().unwrap();
+        let original = ctx.evaluate("Symbol.iterator", None).unwrap();
+        ctx.evaluate("globalThis.Symbol = {iterator: 'fake'}", None)
+            .unwrap();
+
+        let iterator = JSValue::well_known_symbol(WellKnownSymbol::Iterator, &ctx).unwrap();
+        assert_eq!(iterator, original);
+        assert!(JSValue::new_symbol(Some("test"), &ctx).unwrap().is_symbol());
+
+        let array = ctx.evaluate("[1, 2, 3]", None).unwrap();
+        assert_eq!(array.iter().unwrap().count(), 3);
+
+        let words = vec!["one".to_string(), "two".to_string()];
+        let words = JSValue::new_iterator(words.into_iter(), &ctx).unwrap();
+        ctx.global_object().set_property("words", &words).unwrap();
+        let result = ctx.evaluate("[...words].join(',')", None).unwrap();
+        assert_eq!(result.try_convert::<String>().unwrap(), "one,two");
+    }
+
+    #[test]
+    fn creates_iterators() {
+        let ctx = JSContext::new().unwrap();
+        let words = vec!["one".to_string(), "two".to_string()];
+        let iterator = JSValue::new_iterator(words.into_iter(), &ctx).unwrap();
+        ctx.global_object()
+            .set_property("iterator", &iterator)
+            .unwrap();
+
+        let result = ctx.evaluate("[...iterator].join(',')", None).unwrap();
+        assert_eq!(result.try_convert::<String>().unwrap(), "one,two");
+
+        // Once it's exhausted it stays that way
+        let result = ctx
+            .evaluate(
+                "let r = iterator.next(); r.done && r.value === undefined",
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.try_convert::<bool>().unwrap(), true);
+    }
+
+    #[test]
+    fn iterators_are_lazy() {
+        let ctx = JSContext::new().unwrap();
+        let taken = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = taken.clone();
+        let numbers = (0..).map(move |n| {
+            counter.set(counter.get() + 1);
+            n as f64
+        });
+        let iterator = JSValue::new_iterator(numbers, &ctx).unwrap();
+        ctx.global_object()
+            .set_property("numbers", &iterator)
+            .unwrap();
+
+        let result = ctx
+            .evaluate(
+                "let total = 0; for (const n of numbers) { if (n > 2) break; total += n; } total",
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.try_convert::<f64>().unwrap(), 3.0);
+        assert_eq!(taken.get(), 4);
+    }
+
+    fn collect_strings(value: &JSValue) -> Vec<String> {
+        value
+            .iter()
+            .unwrap()
+            .map(|item| item.unwrap().try_convert().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn iterates_built_in_iterables() {
+        let ctx = JSContext::new().unwrap();
+
+        let array = ctx.evaluate("['one', 'two']", None).unwrap();
+        assert_eq!(collect_strings(&array), vec!["one", "two"]);
+
+        let set = ctx.evaluate("new Set(['a', 'b', 'a'])", None).unwrap();
+        assert_eq!(collect_strings(&set), vec!["a", "b"]);
+
+        let map = ctx.evaluate("new Map([['k', 'v']])", None).unwrap();
+        let entries: Vec<Vec<String>> = map
+            .iter()
+            .unwrap()
+            .map(|entry| entry.unwrap().try_convert().unwrap())
+            .collect();
+        assert_eq!(entries, vec![vec!["k".to_string(), "v".to_string()]]);
+
+        let string = JSValue::try_new_from("hi", &ctx).unwrap();
+        assert_eq!(collect_strings(&string), vec!["h", "i"]);
+    }
+
+    #[test]
+    fn iterates_generators_and_custom_iterables() {
+        let ctx = JSContext::new().unwrap();
+
+        let generator = ctx
+            .evaluate("(function* () { yield 'x'; yield 'y'; })()", None)
+            .unwrap();
+        assert_eq!(collect_strings(&generator), vec!["x", "y"]);
+
+        let custom = ctx
+            .evaluate(
+                "({ [Symbol.iterator]() { \
+                    let i = 0; \
+                    return { next: () => ({ done: i > 1, value: `item${i++}` }) }; \
+                 } })",
+                None,
+            )
+            .unwrap();
+        assert_eq!(collect_strings(&custom), vec!["item0", "item1"]);
+    }
+
+    #[test]
+    fn calls_return_when_dropped_early() {
+        let ctx = JSContext::new().unwrap();
+        let generator = ctx
+            .evaluate(
+                "globalThis.finished = false; \
+                 (function* () { try { yield 1; yield 2; } finally { finished = true; } })()",
+                None,
+            )
+            .unwrap();
+
+        {
+            let mut iter = generator.iter().unwrap();
+            let first: f64 = iter.next().unwrap().unwrap().try_convert().unwrap();
+            assert_eq!(first, 1.0);
+        }
+
+        let finished = ctx.evaluate("finished", None).unwrap();
+        assert_eq!(finished.try_convert::<bool>().unwrap(), true);
+    }
+
+    #[test]
+    fn fails_to_iterate_non_iterables() {
+        let ctx = JSContext::new().unwrap();
+        let object = ctx.evaluate("({})", None).unwrap();
+        assert!(matches!(
+            object.iter().err().unwrap(),
+            EsperantoError::ValueError(JSValueError::IsNotIterable)
+        ));
+
+        let throwing = ctx
+            .evaluate(
+                "({ [Symbol.iterator]() { return { next() { throw new Error('nope'); } }; } })",
+                None,
+            )
+            .unwrap();
+        let mut iter = throwing.iter().unwrap();
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
 }